@@ -0,0 +1,119 @@
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+
+/// 占位图片（图片已删除、防盗链提示等）的特征。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signature {
+    /// 图片数据的 MD5（小写十六进制）
+    Md5(String),
+    /// 图片数据的字节数
+    Size(usize),
+    /// 图片尺寸（宽，高）
+    Dimensions(u32, u32),
+    /// 页面地址的结尾，用于来源固定地址的占位图片
+    AddressSuffix(String),
+}
+
+impl Signature {
+    pub fn md5<S: Into<String>>(hex: S) -> Self {
+        Signature::Md5(hex.into().to_lowercase())
+    }
+
+    pub fn matches(&self, address: &str, bytes: &[u8]) -> bool {
+        match self {
+            Signature::Md5(hex) => &format!("{:x}", md5::compute(bytes)) == hex,
+            Signature::Size(size) => bytes.len() == *size,
+            Signature::Dimensions(width, height) => image::load_from_memory(bytes)
+                .map(|image| image.dimensions() == (*width, *height))
+                .unwrap_or(false),
+            Signature::AddressSuffix(suffix) => address.ends_with(suffix.as_str()),
+        }
+    }
+}
+
+/// 页面（地址和图片数据）是否匹配任意一个占位图片特征。
+pub fn is_placeholder(signatures: &[Signature], address: &str, bytes: &[u8]) -> bool {
+    signatures.iter().any(|signature| signature.matches(address, bytes))
+}
+
+/// 判定为重复页面的默认最大汉明距离。
+pub const DUPLICATE_DISTANCE: u32 = 4;
+
+/// 计算图片的差异哈希（dHash），用于比较图片是否相似。
+///
+/// 图片缩小为 9x8 的灰度图，每行相邻像素比较亮度得到 64 位。
+pub fn dhash(image: &DynamicImage) -> u64 {
+    let gray = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if gray.get_pixel(x, y)[0] < gray.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+
+    hash
+}
+
+/// 解码图片数据并计算 dHash，无法解码时返回 `None`。
+pub fn dhash_bytes(bytes: &[u8]) -> Option<u64> {
+    image::load_from_memory(bytes).ok().map(|image| dhash(&image))
+}
+
+/// 两个哈希之间的汉明距离。
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// 找出与之前页面重复的页面，返回 `(重复页面下标, 首次出现的页面下标)`。
+///
+/// `hashes` 按页面顺序排列，值为 `None` 的页面（无法解码）不参与比较。
+pub fn find_duplicates(hashes: &[Option<u64>], max_distance: u32) -> Vec<(usize, usize)> {
+    let mut duplicates = vec![];
+    for (i, hash) in hashes.iter().enumerate() {
+        if let Some(hash) = hash {
+            let original = hashes[..i].iter().enumerate().find(|(j, other)| {
+                !duplicates.iter().any(|(d, _)| d == j)
+                    && other.map(|other| distance(*hash, other) <= max_distance).unwrap_or(false)
+            });
+            if let Some((j, _)) = original {
+                duplicates.push((i, j));
+            }
+        }
+    }
+
+    duplicates
+}
+
+#[test]
+fn test_signature() {
+    let (address, bytes) = ("https://ehgt.org/g/509.gif", b"placeholder");
+    assert!(!Signature::md5("D2D7B3B5FC0FF6B4E4DB0A8AE4E2F1E0").matches(address, bytes));
+    assert!(Signature::md5(format!("{:x}", md5::compute(bytes))).matches(address, bytes));
+    assert!(Signature::Size(11).matches(address, bytes));
+    assert!(!Signature::Dimensions(1, 1).matches(address, bytes));
+    assert!(Signature::AddressSuffix(String::from("/509.gif")).matches(address, bytes));
+    assert!(!Signature::AddressSuffix(String::from("/509.gif")).matches("https://ehgt.org/g/1.gif", bytes));
+    assert!(is_placeholder(&[Signature::Size(1), Signature::Size(11)], address, bytes));
+    assert!(!is_placeholder(&[], address, bytes));
+}
+
+#[test]
+fn test_find_duplicates() {
+    use image::{Rgb, RgbImage};
+
+    let gradient = DynamicImage::ImageRgb8(RgbImage::from_fn(90, 80, |x, _| Rgb([(x * 2) as u8; 3])));
+    let reversed = DynamicImage::ImageRgb8(RgbImage::from_fn(90, 80, |x, _| Rgb([255 - (x * 2) as u8; 3])));
+    // 同一图片的不同尺寸版本
+    let resized = gradient.resize_exact(180, 160, FilterType::Nearest);
+    let hashes = vec![
+        Some(dhash(&gradient)),
+        Some(dhash(&reversed)),
+        None,
+        Some(dhash(&resized)),
+        Some(dhash(&gradient)),
+    ];
+    assert_eq!(0, distance(hashes[0].unwrap(), hashes[3].unwrap()));
+    assert_eq!(vec![(3, 0), (4, 0)], find_duplicates(&hashes, DUPLICATE_DISTANCE));
+}
@@ -0,0 +1,585 @@
+use crate::detection::{self, Signature};
+use crate::extractors::{Extractor, PageData};
+use crate::helper::mime;
+use crate::processing::Pipeline;
+use crate::progress::{silent, Listener};
+use crate::sidecar;
+use crate::{error::*, models::*};
+use failure::Error;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// 页面失败的类型。
+#[derive(Debug, Clone, PartialEq)]
+pub enum FailureKind {
+    Error,
+    /// 来源返回了占位图片
+    Placeholder,
+    /// 与第 `of` 页重复
+    Duplicate { of: usize },
+}
+
+/// 下载失败的页面。
+#[derive(Debug, Clone)]
+pub struct Failure {
+    pub n: usize,
+    pub address: String,
+    pub error: String,
+    pub kind: FailureKind,
+}
+
+impl Failure {
+    fn new(n: usize, address: &str, error: &Error) -> Self {
+        let kind = match error.downcast_ref::<PageError>() {
+            Some(PageError::Placeholder { .. }) => FailureKind::Placeholder,
+            Some(PageError::Duplicate { of, .. }) => FailureKind::Duplicate { of: *of },
+            _ => FailureKind::Error,
+        };
+        Self {
+            n,
+            address: address.to_string(),
+            error: error.to_string(),
+            kind,
+        }
+    }
+
+    /// 是否为检测出的占位图片或重复页面。
+    pub fn is_detected(&self) -> bool {
+        self.kind != FailureKind::Error
+    }
+}
+
+/// 章节下载结果。
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub total: usize,
+    pub downloaded: usize,
+    pub skipped: usize,
+    pub failed: Vec<Failure>,
+    /// 开启 `filter_detected` 时被过滤（未保存文件）的占位图片和重复页面
+    pub filtered: Vec<Failure>,
+}
+
+impl Summary {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// 下载失败的章节。
+#[derive(Debug, Clone)]
+pub struct ChapterFailure {
+    pub which: u32,
+    pub title: String,
+    pub error: String,
+}
+
+/// 整部漫画的下载结果，单个章节的失败不会中断其它章节。
+#[derive(Debug, Clone, Default)]
+pub struct ComicSummary {
+    /// 成功处理的章节（`which`）及其下载结果，其中仍可能包含失败的页面
+    pub chapters: Vec<(u32, Summary)>,
+    pub failed: Vec<ChapterFailure>,
+}
+
+impl ComicSummary {
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.chapters.iter().all(|(_, s)| s.is_complete())
+    }
+}
+
+/// 章节选择。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selection {
+    All,
+    /// 按 `which` 选择
+    Which(Vec<u32>),
+    /// 按从标题解析的话数选择（闭区间），无法解析话数的章节不会被选中
+    Numbers(Vec<RangeInclusive<f64>>),
+    /// 按章节列表中的位置选择（从 1 开始，闭区间）
+    Indices(Vec<RangeInclusive<usize>>),
+    /// 章节列表末尾（最新）的 N 个章节
+    Latest(usize),
+    /// 按分组名称（见 `Chapter::group`）选择
+    Group(String),
+}
+
+impl Selection {
+    /// 解析位置范围，例如 `1-20,25`。
+    pub fn indices(ranges: &str) -> Result<Self> {
+        Ok(Selection::Indices(parse_ranges(ranges)?))
+    }
+
+    /// 解析话数范围，例如 `1-20,25.5`。
+    pub fn numbers(ranges: &str) -> Result<Self> {
+        Ok(Selection::Numbers(parse_ranges(ranges)?))
+    }
+
+    /// 返回被选中章节在列表中的下标，保持列表顺序。
+    pub fn select(&self, chapters: &[Chapter]) -> Vec<usize> {
+        let len = chapters.len();
+        (0..len)
+            .filter(|&i| {
+                let chapter = &chapters[i];
+                match self {
+                    Selection::All => true,
+                    Selection::Which(which) => which.contains(&chapter.which),
+                    Selection::Numbers(ranges) => chapter
+                        .number()
+                        .map(|n| ranges.iter().any(|r| r.contains(&n)))
+                        .unwrap_or(false),
+                    Selection::Indices(ranges) => ranges.iter().any(|r| r.contains(&(i + 1))),
+                    Selection::Latest(count) => i + count >= len,
+                    Selection::Group(group) => chapter.group == *group,
+                }
+            })
+            .collect()
+    }
+}
+
+fn parse_ranges<T: FromStr + PartialOrd + Copy>(text: &str) -> Result<Vec<RangeInclusive<T>>> {
+    let parse = |s: &str| {
+        s.trim()
+            .parse::<T>()
+            .map_err(|_| err_msg(format!("Invalid range `{}`", text)))
+    };
+    let mut ranges = vec![];
+    for part in text.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let range = match part.find('-') {
+            Some(pos) if pos > 0 => parse(&part[..pos])?..=parse(&part[pos + 1..])?,
+            _ => {
+                let n = parse(part)?;
+                n..=n
+            }
+        };
+        if range.start() > range.end() {
+            return Err(err_msg(format!("Invalid range `{}`", part)));
+        }
+        ranges.push(range);
+    }
+    if ranges.is_empty() {
+        return Err(err_msg(format!("Invalid range `{}`", text)));
+    }
+
+    Ok(ranges)
+}
+
+/// 将章节下载到目录的下载器。
+///
+/// 页面按位置（从 1 开始）命名，扩展名取决于实际的图片类型，下载完成后会更新章节中页面的 `fname`。
+pub struct Downloader<'a> {
+    extr: &'a (dyn Extractor + Sync + Send),
+    concurrency: usize,
+    skip_existing: bool,
+    pipeline: Pipeline,
+    listener: Listener,
+    placeholders: Vec<Signature>,
+    detect_duplicates: bool,
+    filter_detected: bool,
+    write_metadata: bool,
+}
+
+impl<'a> Downloader<'a> {
+    pub fn new(extr: &'a (dyn Extractor + Sync + Send)) -> Self {
+        Self {
+            extr,
+            concurrency: 4,
+            skip_existing: true,
+            pipeline: Pipeline::new(),
+            listener: silent(),
+            placeholders: vec![],
+            detect_duplicates: false,
+            filter_detected: false,
+            write_metadata: true,
+        }
+    }
+
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 跳过目录中已存在的页面（用于断点续传），默认开启。
+    pub fn skip_existing(mut self, skip_existing: bool) -> Self {
+        self.skip_existing = skip_existing;
+        self
+    }
+
+    /// 应用自己的后处理器，在来源要求的处理器之后执行。
+    pub fn pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.pipeline = pipeline;
+        self
+    }
+
+    pub fn listener(mut self, listener: Listener) -> Self {
+        self.listener = listener;
+        self
+    }
+
+    /// 额外的占位图片特征，与来源自身的特征（`Extractor::placeholders`）一起检查。
+    pub fn placeholders(mut self, placeholders: Vec<Signature>) -> Self {
+        self.placeholders = placeholders;
+        self
+    }
+
+    /// 检测章节内的重复页面（感知哈希），重复的页面会被删除，默认关闭。
+    pub fn detect_duplicates(mut self, detect_duplicates: bool) -> Self {
+        self.detect_duplicates = detect_duplicates;
+        self
+    }
+
+    /// 在漫画和章节目录中写入元数据文件（参考 `sidecar`），默认开启。
+    pub fn write_metadata(mut self, write_metadata: bool) -> Self {
+        self.write_metadata = write_metadata;
+        self
+    }
+
+    /// 将检测出的占位图片和重复页面记录在 `Summary.filtered` 中，而不是作为失败报告。
+    ///
+    /// 这些页面的文件不会保留，但仍留在章节中，以免之后的页面与按位置命名的文件错位。
+    pub fn filter_detected(mut self, filter_detected: bool) -> Self {
+        self.filter_detected = filter_detected;
+        self
+    }
+
+    /// 下载章节的全部页面到 `dir`，章节尚未解析页面时会先解析。
+    pub fn download(&self, chapter: &mut Chapter, dir: &Path) -> Result<Summary> {
+        let mut summary = Summary::default();
+        if chapter.pages.is_empty() {
+            for (i, page) in self
+                .extr
+                .pages_iter_with(chapter, self.listener.clone())?
+                .enumerate()
+            {
+                if let Err(e) = page {
+                    summary.failed.push(Failure::new(i + 1, "", &e));
+                }
+            }
+        }
+        fs::create_dir_all(dir)?;
+        summary.total = chapter.pages.len();
+
+        let snapshot = chapter.clone();
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(vec![]);
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(snapshot.pages.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= snapshot.pages.len() {
+                        break;
+                    }
+                    let result = self.download_page(&snapshot, i, dir);
+                    results.lock().unwrap().push((i, result));
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(i, _)| *i);
+        if self.detect_duplicates {
+            let hashes = results
+                .iter()
+                .map(|(_, result)| result.as_ref().ok().and_then(|(_, _, hash)| *hash))
+                .collect::<Vec<_>>();
+            for (i, j) in detection::find_duplicates(&hashes, detection::DUPLICATE_DISTANCE) {
+                if let Ok((saved, _, _)) = &results[i].1 {
+                    fs::remove_file(dir.join(&saved.fname))?;
+                }
+                results[i].1 = Err(PageError::Duplicate { n: i + 1, of: j + 1 }.into());
+            }
+        }
+        for (i, result) in results {
+            let page = &mut chapter.pages[i];
+            match result {
+                Ok((saved, skipped, _)) => {
+                    if skipped {
+                        summary.skipped += 1;
+                    } else {
+                        summary.downloaded += 1;
+                    }
+                    page.address = saved.address;
+                    page.fname = saved.fname;
+                    page.fmime = saved.fmime;
+                }
+                Err(e) => {
+                    let failure = Failure::new(i + 1, &page.address, &e);
+                    if self.filter_detected && failure.is_detected() {
+                        summary.filtered.push(failure);
+                    } else {
+                        summary.failed.push(failure);
+                    }
+                }
+            }
+        }
+        if self.write_metadata {
+            sidecar::write_chapter(dir, chapter)?;
+        }
+
+        Ok(summary)
+    }
+
+    /// 下载漫画中被选中的章节到 `root`，目录布局见 `comic_dir` 和 `chapter_dir`。
+    ///
+    /// 漫画尚未获取章节列表时会先获取，单个章节失败时记录并继续下载后续章节。
+    pub fn download_comic(&self, comic: &mut Comic, selection: &Selection, root: &Path) -> Result<ComicSummary> {
+        if comic.chapters.is_empty() {
+            self.extr.fetch_chapters(comic)?;
+        }
+        let comic_dir = comic_dir(root, comic);
+        if self.write_metadata {
+            sidecar::write_comic(&comic_dir, comic)?;
+        }
+        let mut summary = ComicSummary::default();
+        for i in selection.select(&comic.chapters) {
+            let chapter = &mut comic.chapters[i];
+            let dir = chapter_dir(&comic_dir, chapter);
+            match self.download(chapter, &dir) {
+                Ok(chapter_summary) => summary.chapters.push((chapter.which, chapter_summary)),
+                Err(e) => summary.failed.push(ChapterFailure {
+                    which: chapter.which,
+                    title: chapter.title.clone(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        Ok(summary)
+    }
+
+    // 返回保存后的页面、是否因已存在而跳过，以及用于检测重复的哈希
+    fn download_page(&self, chapter: &Chapter, i: usize, dir: &Path) -> Result<(Page, bool, Option<u64>)> {
+        let n = i + 1;
+        let page = &chapter.pages[i];
+        if self.skip_existing {
+            if let Some(fname) = find_existing(dir, n)? {
+                let mut saved = page.clone();
+                saved.fmime = mime::from_address(&fname)
+                    .unwrap_or(mime::DEFAULT_MIME)
+                    .to_string();
+                let hash = if self.detect_duplicates {
+                    detection::dhash_bytes(&fs::read(dir.join(&fname))?)
+                } else {
+                    None
+                };
+                saved.fname = fname;
+                return Ok((saved, true, hash));
+            }
+        }
+        let mut page = page.clone();
+        page.fname = Page::fname(&page.address, &n);
+        let data = self.extr.download_page_with(chapter, &page, &self.listener)?;
+        if detection::is_placeholder(&self.placeholders, &data.page.address, &data.bytes) {
+            return Err(PageError::Placeholder {
+                address: data.page.address,
+            }
+            .into());
+        }
+        let PageData { page, bytes } = self.pipeline.run(data)?;
+        let hash = if self.detect_duplicates {
+            detection::dhash_bytes(&bytes)
+        } else {
+            None
+        };
+        save(dir, &page.fname, &bytes)?;
+
+        Ok((page, false, hash))
+    }
+}
+
+/// 去掉文件名中不允许或容易引起问题的字符。
+pub fn sanitize_fname(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>();
+    let name = name.trim().trim_end_matches('.').trim();
+    if name.is_empty() {
+        String::from("_")
+    } else {
+        name.to_string()
+    }
+}
+
+/// 漫画在下载根目录中的位置：`<root>/<漫画标题>`。
+pub fn comic_dir(root: &Path, comic: &Comic) -> PathBuf {
+    root.join(sanitize_fname(&comic.title))
+}
+
+/// 章节在漫画目录中的位置：`<comic_dir>/<which 补零> <章节标题>`。
+pub fn chapter_dir(comic_dir: &Path, chapter: &Chapter) -> PathBuf {
+    comic_dir.join(sanitize_fname(&format!("{:04} {}", chapter.which, chapter.title)))
+}
+
+/// 查找目录中第 `n` 页对应的文件（不限扩展名）。
+pub fn find_existing(dir: &Path, n: usize) -> Result<Option<String>> {
+    let stem = n.to_string();
+    if !dir.exists() {
+        return Ok(None);
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_stem = path.file_stem().and_then(|s| s.to_str());
+        let extension = path.extension().and_then(|s| s.to_str());
+        if file_stem == Some(&stem) && extension != Some(TEMP_EXTENSION) && path.is_file() {
+            return Ok(path.file_name().and_then(|s| s.to_str()).map(|s| s.to_string()));
+        }
+    }
+
+    Ok(None)
+}
+
+const TEMP_EXTENSION: &str = "part";
+
+/// 先写入临时文件再重命名，避免中断后留下不完整的文件。
+pub fn save(dir: &Path, fname: &str, bytes: &[u8]) -> Result<PathBuf> {
+    let path = dir.join(fname);
+    let temp_path = dir.join(format!("{}.{}", fname, TEMP_EXTENSION));
+    fs::write(&temp_path, bytes)?;
+    fs::rename(&temp_path, &path)?;
+
+    Ok(path)
+}
+
+#[test]
+fn test_download() {
+    let extr = crate::extractors::get_extr("www.manhuagui.com").unwrap();
+    let dir = std::env::temp_dir().join("mikack-test-download");
+    let _ = fs::remove_dir_all(&dir);
+    let mut chapter = Chapter::from_url("https://www.manhuagui.com/comic/2863/271796.html");
+    chapter.push_page(Page::new(0, "data:image/gif;base64,R0lGODlhAQABAAAAACw="));
+    chapter.push_page(Page::new(1, "data:image/png;base64,iVBORw0KGgo="));
+    chapter.push_page(Page::new(2, "data:,invalid"));
+
+    let summary = Downloader::new(&**extr).download(&mut chapter, &dir).unwrap();
+    assert_eq!(3, summary.total);
+    assert_eq!(3, summary.downloaded);
+    assert_eq!("1.gif", chapter.pages[0].fname);
+    assert_eq!("2.png", chapter.pages[1].fname);
+    assert_eq!("3", chapter.pages[2].fname);
+    assert!(dir.join("2.png").exists());
+
+    let summary = Downloader::new(&**extr).download(&mut chapter, &dir).unwrap();
+    assert_eq!(3, summary.skipped);
+    assert_eq!("image/png", chapter.pages[1].fmime);
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_layout() {
+    let root = Path::new("/downloads");
+    let comic = Comic::new("Fate/stay night: <UBW>", "https://www.example.com/comic/1");
+    let mut chapter = Chapter::new("第1话 ...", "https://www.example.com/comic/1/1.html", 0);
+    chapter.which = 12;
+    let comic_dir = comic_dir(root, &comic);
+    assert_eq!(Path::new("/downloads/Fate_stay night_ _UBW_"), comic_dir);
+    assert_eq!(
+        Path::new("/downloads/Fate_stay night_ _UBW_/0012 第1话"),
+        chapter_dir(&comic_dir, &chapter)
+    );
+}
+
+#[test]
+fn test_selection() {
+    let mut chapters = vec![];
+    for (i, title) in ["第1话", "第2话", "第2.5话", "第3话", "番外"].iter().enumerate() {
+        chapters.push(Chapter::new(*title, format!("https://www.example.com/{}.html", i), i as u32 + 1));
+    }
+    let mut volume = Chapter::new("第1卷", "https://www.example.com/v1.html", 10001);
+    volume.group = String::from("单行本");
+    chapters.push(volume);
+
+    assert_eq!(vec![0, 1, 2, 3, 4, 5], Selection::All.select(&chapters));
+    assert_eq!(vec![1, 5], Selection::Which(vec![2, 10001]).select(&chapters));
+    assert_eq!(vec![1, 2, 3], Selection::numbers("2-3").unwrap().select(&chapters));
+    assert_eq!(vec![0, 1, 4], Selection::indices("1-2, 5").unwrap().select(&chapters));
+    assert_eq!(vec![4, 5], Selection::Latest(2).select(&chapters));
+    assert_eq!(vec![5], Selection::Group(String::from("单行本")).select(&chapters));
+    assert!(Selection::indices("3-1").is_err());
+    assert!(Selection::indices("a-b").is_err());
+    assert!(Selection::indices("").is_err());
+}
+
+#[test]
+fn test_download_comic() {
+    let extr = crate::extractors::get_extr("www.manhuagui.com").unwrap();
+    let root = std::env::temp_dir().join("mikack-test-download-comic");
+    let _ = fs::remove_dir_all(&root);
+    let mut comic = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    for which in 1..=3 {
+        let mut chapter = Chapter::new(
+            format!("第{}话", which),
+            format!("https://www.manhuagui.com/comic/2863/{}.html", which),
+            which,
+        );
+        if which != 2 {
+            chapter.push_page(Page::new(0, "data:image/png;base64,iVBORw0KGgo="));
+        }
+        comic.push_chapter(chapter);
+    }
+    // 第 2 话没有页面，会因解析失败而失败
+    comic.chapters[1].url = String::from("data:,invalid");
+
+    let summary = Downloader::new(&**extr)
+        .download_comic(&mut comic, &Selection::All, &root)
+        .unwrap();
+    assert_eq!(vec![1, 3], summary.chapters.iter().map(|(which, _)| *which).collect::<Vec<_>>());
+    assert_eq!(1, summary.failed.len());
+    assert_eq!(2, summary.failed[0].which);
+    assert!(!summary.is_complete());
+    let comic_dir = comic_dir(&root, &comic);
+    assert!(chapter_dir(&comic_dir, &comic.chapters[2]).join("1.png").exists());
+    assert_eq!(3, sidecar::read_comic(&comic_dir).unwrap().chapters.len());
+    let chapter = sidecar::read_chapter(&chapter_dir(&comic_dir, &comic.chapters[2])).unwrap();
+    assert_eq!("1.png", chapter.pages[0].fname);
+    let _ = fs::remove_dir_all(&root);
+}
+
+#[test]
+fn test_detection() {
+    use image::{DynamicImage, ImageOutputFormat, Rgb, RgbImage};
+
+    let extr = crate::extractors::get_extr("www.manhuagui.com").unwrap();
+    let dir = std::env::temp_dir().join("mikack-test-download-detection");
+    let _ = fs::remove_dir_all(&dir);
+    let png = |f: fn(u32) -> u8| {
+        let mut bytes = vec![];
+        DynamicImage::ImageRgb8(RgbImage::from_fn(90, 80, |x, _| Rgb([f(x); 3])))
+            .write_to(&mut bytes, ImageOutputFormat::Png)
+            .unwrap();
+        format!("data:image/png;base64,{}", base64::encode(&bytes))
+    };
+    let mut chapter = Chapter::from_url("https://www.manhuagui.com/comic/2863/271796.html");
+    chapter.push_page(Page::new(0, png(|x| (x * 2) as u8)));
+    chapter.push_page(Page::new(1, png(|x| 255 - (x * 2) as u8)));
+    chapter.push_page(Page::new(2, png(|x| (x * 2) as u8)));
+    chapter.push_page(Page::new(3, "data:image/gif;base64,R0lGODlhAQABAAAAACw="));
+
+    let downloader = Downloader::new(&**extr)
+        .placeholders(vec![Signature::Size(14)])
+        .detect_duplicates(true);
+    let summary = downloader.download(&mut chapter.clone(), &dir).unwrap();
+    assert_eq!(2, summary.downloaded);
+    let kinds = summary.failed.iter().map(|f| f.kind.clone()).collect::<Vec<_>>();
+    assert_eq!(vec![FailureKind::Duplicate { of: 1 }, FailureKind::Placeholder], kinds);
+    assert!(!dir.join("3.png").exists());
+
+    let summary = downloader.filter_detected(true).download(&mut chapter, &dir).unwrap();
+    assert_eq!(2, summary.skipped);
+    assert!(summary.failed.is_empty());
+    assert_eq!(2, summary.filtered.len());
+    // 被过滤的页面仍保留在原位置
+    assert_eq!(4, chapter.pages.len());
+    assert_eq!(vec!["1.png", "2.png"], chapter.pages[..2].iter().map(|p| &p.fname).collect::<Vec<_>>());
+    assert!(!dir.join("3.png").exists());
+    let _ = fs::remove_dir_all(&dir);
+}
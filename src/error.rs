@@ -2,3 +2,13 @@ pub use failure::err_msg;
 use failure::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug, Fail)]
+pub enum PageError {
+    #[fail(display = "Page address expired with status {}: {}", status, address)]
+    Expired { status: u16, address: String },
+    #[fail(display = "Page is a placeholder image: {}", address)]
+    Placeholder { address: String },
+    #[fail(display = "Page {} is a duplicate of page {}", n, of)]
+    Duplicate { n: usize, of: usize },
+}
@@ -0,0 +1,152 @@
+use crate::helper::mime;
+use crate::{error::*, models::*};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub mod cbz;
+pub mod epub;
+pub mod pdf;
+pub mod transform;
+
+/// 阅读方向。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    LeftToRight,
+    RightToLeft,
+}
+
+impl Default for Direction {
+    fn default() -> Self {
+        Direction::LeftToRight
+    }
+}
+
+/// 已下载到本地的页面文件。
+#[derive(Debug, Clone)]
+pub struct PageFile {
+    pub path: PathBuf,
+    pub mime: String,
+}
+
+impl PageFile {
+    fn new(path: PathBuf) -> Self {
+        let mime = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(mime::from_address)
+            .unwrap_or(mime::DEFAULT_MIME)
+            .to_string();
+        Self { path, mime }
+    }
+
+    pub fn extension(&self) -> &str {
+        mime::to_extension(&self.mime).unwrap_or("bin")
+    }
+}
+
+/// 按页面顺序列出章节目录中的图片文件。
+///
+/// 优先使用章节中页面的 `fname`，缺失时按文件名中的数字排序目录中的图片。
+pub fn page_files(chapter: &Chapter, dir: &Path) -> Result<Vec<PageFile>> {
+    let from_pages = chapter
+        .pages
+        .iter()
+        .map(|page| dir.join(&page.fname))
+        .collect::<Vec<_>>();
+    if !from_pages.is_empty() && from_pages.iter().all(|path| path.is_file()) {
+        return Ok(from_pages.into_iter().map(PageFile::new).collect());
+    }
+
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file = PageFile::new(path);
+        if file.path.is_file() && file.mime != mime::DEFAULT_MIME {
+            files.push(file);
+        }
+    }
+    files.sort_by_key(|file| {
+        let stem = file
+            .path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_string();
+        (stem.parse::<u64>().unwrap_or(u64::MAX), stem)
+    });
+    if files.is_empty() {
+        return Err(err_msg(format!("No page files found in `{}`", dir.display())));
+    }
+
+    Ok(files)
+}
+
+/// 补零后的页面文件名，保证按名称排序即为页面顺序。
+pub fn padded_fname(i: usize, total: usize, extension: &str) -> String {
+    let width = total.to_string().len().max(3);
+    format!("{:0width$}.{}", i + 1, extension, width = width)
+}
+
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // XML 1.0 不允许的控制字符
+            c if (c as u32) < 0x20 && c != '\t' && c != '\n' && c != '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 当前 UTC 时间，格式为 `2020-03-01T08:00:00Z`。
+pub fn utc_timestamp() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    format_timestamp(secs)
+}
+
+pub fn format_timestamp(secs: i64) -> String {
+    let days = secs.div_euclid(86400);
+    let rem = secs.rem_euclid(86400);
+    // 公历日期换算（Howard Hinnant 的 civil_from_days 算法）
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[test]
+fn test_format_timestamp() {
+    assert_eq!("1970-01-01T00:00:00Z", format_timestamp(0));
+    assert_eq!("2020-02-29T12:34:56Z", format_timestamp(1582979696));
+}
+
+#[test]
+fn test_padded_fname() {
+    assert_eq!("001.jpg", padded_fname(0, 20, "jpg"));
+    assert_eq!("0100.png", padded_fname(99, 1200, "png"));
+    assert_eq!("a &amp; b &lt;c&gt;", escape_xml("a & b <c>\u{1}"));
+}
@@ -0,0 +1,239 @@
+use super::*;
+use super::transform::{load_pages, PageImage, Transform};
+use crate::download;
+use std::io::Write;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+// 卷数（无法识别时为 None）及其中已下载的 `(章节, 章节目录)`
+type VolumeGroup<'c> = (Option<f64>, Vec<(&'c Chapter, PathBuf)>);
+
+/// 将已下载的章节打包为 CBZ（包含 ComicInfo.xml）。
+pub struct Cbz<'a> {
+    comic: &'a Comic,
+    direction: Direction,
+    transform: Transform,
+}
+
+impl<'a> Cbz<'a> {
+    pub fn new(comic: &'a Comic) -> Self {
+        Self {
+            comic,
+            direction: Default::default(),
+            transform: Default::default(),
+        }
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// 导出前对页面图片的变换。
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// 将 `dir` 中的章节页面写入 `output`。
+    pub fn write_chapter(&self, chapter: &Chapter, dir: &Path, output: &Path) -> Result<()> {
+        let pages = load_pages(chapter, dir, &self.transform, self.direction)?;
+        self.write_archive(chapter, &pages, output)
+    }
+
+    /// 将同一卷的多个章节按顺序写入一个 `output`，`chapters` 为 `(章节, 章节目录)`。
+    pub fn write_volume(&self, volume: f64, chapters: &[(&Chapter, PathBuf)], output: &Path) -> Result<()> {
+        let first = chapters
+            .first()
+            .ok_or_else(|| err_msg(format!("No chapters in volume {}", volume)))?
+            .0;
+        let mut pages = vec![];
+        for (chapter, dir) in chapters {
+            pages.append(&mut load_pages(chapter, dir, &self.transform, self.direction)?);
+        }
+        // 以卷为单位的章节，排序沿用第一个章节的 `which`
+        let volume_chapter = Chapter::new(format!("第{}卷", volume), first.url.clone(), first.which);
+        self.write_archive(&volume_chapter, &pages, output)
+    }
+
+    fn write_archive(&self, chapter: &Chapter, pages: &[PageImage], output: &Path) -> Result<()> {
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut zip = ZipWriter::new(fs::File::create(output)?);
+        // 图片已经过压缩，直接存储即可
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for (i, page) in pages.iter().enumerate() {
+            zip.start_file(padded_fname(i, pages.len(), page.extension()), stored)?;
+            zip.write_all(&page.bytes)?;
+        }
+        zip.start_file("ComicInfo.xml", FileOptions::default())?;
+        zip.write_all(comic_info(self.comic, chapter, pages.len(), self.direction).as_bytes())?;
+        zip.finish()?;
+
+        Ok(())
+    }
+
+    /// 按卷打包整部漫画：连续且卷数（见 `Chapter::volume`）相同的章节合并为一个文件，无法识别卷数的章节单独打包。
+    ///
+    /// 章节目录遵循 `download::chapter_dir` 的布局，未下载的章节会被跳过。
+    pub fn write_comic(&self, comic_dir: &Path, output_dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut groups: Vec<VolumeGroup> = vec![];
+        for chapter in &self.comic.chapters {
+            let dir = download::chapter_dir(comic_dir, chapter);
+            if !dir.is_dir() {
+                continue;
+            }
+            let volume = chapter.volume();
+            match groups.last_mut() {
+                Some((Some(last), chapters)) if volume == Some(*last) => chapters.push((chapter, dir)),
+                _ => groups.push((volume, vec![(chapter, dir)])),
+            }
+        }
+
+        let mut outputs = vec![];
+        for (volume, chapters) in groups {
+            let (first, dir) = &chapters[0];
+            let name = match volume {
+                Some(volume) => format!("{} - {:04} 第{}卷.cbz", self.comic.title, first.which, volume),
+                None => format!("{} - {:04} {}.cbz", self.comic.title, first.which, first.title),
+            };
+            let output = output_dir.join(download::sanitize_fname(&name));
+            match volume {
+                Some(volume) => self.write_volume(volume, &chapters, &output)?,
+                None => self.write_chapter(first, dir, &output)?,
+            }
+            outputs.push(output);
+        }
+
+        Ok(outputs)
+    }
+}
+
+/// 生成 ComicInfo.xml（ComicRack 格式，Komga/Kavita 均支持）。
+pub fn comic_info(comic: &Comic, chapter: &Chapter, page_count: usize, direction: Direction) -> String {
+    let mut fields: Vec<(&str, String)> = vec![];
+    fields.push(("Title", chapter.title.clone()));
+    fields.push(("Series", comic.title.clone()));
+    if let Some(number) = chapter.number() {
+        fields.push(("Number", number.to_string()));
+    }
+    if let Some(volume) = chapter.volume() {
+        fields.push(("Volume", volume.to_string()));
+    }
+    if !comic.chapters.is_empty() {
+        fields.push(("Count", comic.chapters.len().to_string()));
+    }
+    if !comic.description.is_empty() {
+        fields.push(("Summary", comic.description.clone()));
+    }
+    fields.push(("Notes", format!("which: {}, url: {}", chapter.which, chapter.url)));
+    if !comic.author.is_empty() {
+        fields.push(("Writer", comic.author.clone()));
+    }
+    if !comic.tags.is_empty() {
+        fields.push(("Genre", comic.tags.join(", ")));
+        fields.push(("Tags", comic.tags.join(", ")));
+    }
+    fields.push(("Web", comic.url.clone()));
+    fields.push(("PageCount", page_count.to_string()));
+    let manga = match direction {
+        Direction::RightToLeft => "YesAndRightToLeft",
+        Direction::LeftToRight => "Unknown",
+    };
+    fields.push(("Manga", manga.to_string()));
+
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    xml.push('\n');
+    xml.push_str(r#"<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#);
+    xml.push('\n');
+    for (name, value) in fields {
+        xml.push_str(&format!("  <{name}>{}</{name}>\n", escape_xml(&value), name = name));
+    }
+    xml.push_str("  <Pages>\n");
+    for i in 0..page_count {
+        if i == 0 {
+            xml.push_str(r#"    <Page Image="0" Type="FrontCover" />"#);
+        } else {
+            xml.push_str(&format!(r#"    <Page Image="{}" />"#, i));
+        }
+        xml.push('\n');
+    }
+    xml.push_str("  </Pages>\n");
+    xml.push_str("</ComicInfo>\n");
+
+    xml
+}
+
+#[test]
+fn test_write_chapter() {
+    use std::io::Read;
+
+    let dir = std::env::temp_dir().join("mikack-test-cbz");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for i in 1..=11 {
+        fs::write(dir.join(format!("{}.jpg", i)), [0xFF, 0xD8, 0xFF, i as u8]).unwrap();
+    }
+    let mut comic = Comic::new("风云全集", "https://www.dm5.com/manhua-fengyunquanji/");
+    comic.author = String::from("马荣成 & 单明");
+    comic.tags = vec![String::from("武侠")];
+    let chapter = Chapter::new("第12卷 第103话", "https://www.dm5.com/m123/", 98);
+    comic.push_chapter(chapter.clone());
+
+    let output = dir.join("out.cbz");
+    Cbz::new(&comic)
+        .direction(Direction::RightToLeft)
+        .write_chapter(&chapter, &dir, &output)
+        .unwrap();
+    let mut archive = zip::ZipArchive::new(fs::File::open(&output).unwrap()).unwrap();
+    assert_eq!(12, archive.len());
+    assert_eq!("001.jpg", archive.by_index(0).unwrap().name());
+    let mut page = vec![];
+    archive.by_name("011.jpg").unwrap().read_to_end(&mut page).unwrap();
+    assert_eq!(vec![0xFF, 0xD8, 0xFF, 11], page);
+    let mut info = String::new();
+    archive.by_name("ComicInfo.xml").unwrap().read_to_string(&mut info).unwrap();
+    assert!(info.contains("<Series>风云全集</Series>"));
+    assert!(info.contains("<Number>103</Number>"));
+    assert!(info.contains("<Volume>12</Volume>"));
+    assert!(info.contains("<Writer>马荣成 &amp; 单明</Writer>"));
+    assert!(info.contains("<Manga>YesAndRightToLeft</Manga>"));
+    // 标题中没有话数时不写入 Number
+    let extra = Chapter::new("番外篇", "https://www.dm5.com/m124/", 99);
+    let info = comic_info(&comic, &extra, 1, Direction::LeftToRight);
+    assert!(!info.contains("<Number>"));
+    assert!(info.contains("which: 99"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_write_comic() {
+    let root = std::env::temp_dir().join("mikack-test-cbz-comic");
+    let _ = fs::remove_dir_all(&root);
+    let mut comic = Comic::new("风云全集", "https://www.dm5.com/manhua-fengyunquanji/");
+    for (which, title) in &[(1, "第1卷 上"), (2, "第1卷 下"), (3, "番外篇"), (4, "第2卷")] {
+        let chapter = Chapter::new(*title, format!("https://www.dm5.com/m{}/", which), *which);
+        let dir = download::chapter_dir(&root.join("comic"), &chapter);
+        fs::create_dir_all(&dir).unwrap();
+        for i in 1..=2 {
+            fs::write(dir.join(format!("{}.jpg", i)), [0xFF, 0xD8, 0xFF, i as u8]).unwrap();
+        }
+        comic.push_chapter(chapter);
+    }
+
+    let outputs = Cbz::new(&comic)
+        .write_comic(&root.join("comic"), &root.join("out"))
+        .unwrap();
+    let names = outputs
+        .iter()
+        .map(|p| p.file_name().unwrap().to_str().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        vec!["风云全集 - 0001 第1卷.cbz", "风云全集 - 0003 番外篇.cbz", "风云全集 - 0004 第2卷.cbz"],
+        names
+    );
+    let archive = zip::ZipArchive::new(fs::File::open(&outputs[0]).unwrap()).unwrap();
+    // 两个章节各 2 页，加上 ComicInfo.xml
+    assert_eq!(5, archive.len());
+    let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,338 @@
+use super::*;
+use super::transform::{load_pages, Transform};
+use crate::download;
+use crate::extractors::get;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use zip::{write::FileOptions, CompressionMethod, ZipWriter};
+
+/// 将已下载的章节导出为固定版式（fixed-layout）的 EPUB 3。
+pub struct Epub<'a> {
+    comic: &'a Comic,
+    direction: Direction,
+    transform: Transform,
+    language: String,
+    cover: Option<(Vec<u8>, String)>,
+}
+
+struct EpubPage {
+    id: String,
+    image_href: String,
+    mime: String,
+    width: u32,
+    height: u32,
+}
+
+impl<'a> Epub<'a> {
+    pub fn new(comic: &'a Comic) -> Self {
+        Self {
+            comic,
+            direction: Default::default(),
+            transform: Default::default(),
+            language: String::from("zh"),
+            cover: None,
+        }
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// 导出前对页面图片的变换。
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn language<S: Into<String>>(mut self, language: S) -> Self {
+        self.language = language.into();
+        self
+    }
+
+    /// 使用指定的封面图片。
+    pub fn cover(mut self, bytes: Vec<u8>) -> Self {
+        let mime = mime::sniff(&bytes).unwrap_or("image/jpeg").to_string();
+        self.cover = Some((bytes, mime));
+        self
+    }
+
+    /// 下载 `Comic.cover` 作为封面，失败时忽略（使用第一页作为封面）。
+    pub fn fetch_cover(self) -> Self {
+        if self.comic.cover.is_empty() {
+            return self;
+        }
+        let bytes = get(&self.comic.cover).and_then(|resp| Ok(resp.bytes()?.to_vec()));
+        match bytes {
+            Ok(bytes) if mime::sniff(&bytes).is_some() => self.cover(bytes),
+            _ => self,
+        }
+    }
+
+    /// 导出 `comic.chapters` 中指定范围（下标）的章节，章节目录遵循 `download::chapter_dir` 的布局。
+    pub fn write_range(&self, comic_dir: &Path, range: RangeInclusive<usize>, output: &Path) -> Result<()> {
+        let chapters = self
+            .comic
+            .chapters
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| range.contains(i))
+            .map(|(_, chapter)| (chapter, download::chapter_dir(comic_dir, chapter)))
+            .filter(|(_, dir)| dir.is_dir())
+            .collect::<Vec<_>>();
+        let chapters = chapters
+            .iter()
+            .map(|(chapter, dir)| (*chapter, dir.as_path()))
+            .collect::<Vec<_>>();
+
+        self.write(&chapters, output)
+    }
+
+    /// 将章节（及其页面所在目录）依次写入 `output`，每个章节成为目录中的一项。
+    pub fn write(&self, chapters: &[(&Chapter, &Path)], output: &Path) -> Result<()> {
+        if chapters.is_empty() {
+            return Err(err_msg("No chapters to export"));
+        }
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut zip = ZipWriter::new(fs::File::create(output)?);
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default();
+
+        // mimetype 必须是第一个且不压缩的文件
+        zip.start_file("mimetype", stored)?;
+        zip.write_all(b"application/epub+zip")?;
+        zip.start_file("META-INF/container.xml", deflated)?;
+        zip.write_all(CONTAINER_XML.as_bytes())?;
+
+        let mut toc = vec![];
+        let mut pages = vec![];
+        for (c, (chapter, dir)) in chapters.iter().enumerate() {
+            let images = load_pages(chapter, dir, &self.transform, self.direction)?;
+            toc.push((chapter.title.clone(), format!("c{:04}_p0001", c + 1)));
+            for (p, image) in images.iter().enumerate() {
+                let id = format!("c{:04}_p{:04}", c + 1, p + 1);
+                let image_href = format!("images/{}.{}", id, image.extension());
+                let (width, height) = image.dimensions()?;
+                zip.start_file(format!("OEBPS/{}", image_href), stored)?;
+                zip.write_all(&image.bytes)?;
+                zip.start_file(format!("OEBPS/pages/{}.xhtml", id), deflated)?;
+                zip.write_all(page_xhtml(&chapter.title, &image_href, width, height).as_bytes())?;
+                pages.push(EpubPage {
+                    id,
+                    image_href,
+                    mime: image.mime.clone(),
+                    width,
+                    height,
+                });
+            }
+        }
+
+        let cover = match &self.cover {
+            Some((bytes, mime)) => {
+                let extension = mime::to_extension(mime).unwrap_or("jpg");
+                let href = format!("images/cover.{}", extension);
+                zip.start_file(format!("OEBPS/{}", href), stored)?;
+                zip.write_all(bytes)?;
+                Some((href, mime.clone()))
+            }
+            None => None,
+        };
+
+        zip.start_file("OEBPS/nav.xhtml", deflated)?;
+        zip.write_all(self.nav_xhtml(&toc).as_bytes())?;
+        zip.start_file("OEBPS/content.opf", deflated)?;
+        zip.write_all(self.content_opf(&pages, cover).as_bytes())?;
+        zip.finish()?;
+
+        Ok(())
+    }
+
+    fn nav_xhtml(&self, toc: &[(String, String)]) -> String {
+        let mut items = String::new();
+        for (title, id) in toc {
+            items.push_str(&format!(
+                "      <li><a href=\"pages/{}.xhtml\">{}</a></li>\n",
+                id,
+                escape_xml(title)
+            ));
+        }
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+  <title>{title}</title>
+</head>
+<body>
+  <nav epub:type="toc" id="toc">
+    <ol>
+{items}    </ol>
+  </nav>
+</body>
+</html>
+"#,
+            title = escape_xml(&self.comic.title),
+            items = items
+        )
+    }
+
+    fn content_opf(&self, pages: &[EpubPage], cover: Option<(String, String)>) -> String {
+        let comic = self.comic;
+        let mut metadata = vec![];
+        metadata.push(format!(r#"<dc:identifier id="uid">{}</dc:identifier>"#, escape_xml(&comic.url)));
+        metadata.push(format!("<dc:title>{}</dc:title>", escape_xml(&comic.title)));
+        metadata.push(format!("<dc:language>{}</dc:language>", escape_xml(&self.language)));
+        if !comic.author.is_empty() {
+            metadata.push(format!("<dc:creator>{}</dc:creator>", escape_xml(&comic.author)));
+        }
+        if !comic.description.is_empty() {
+            metadata.push(format!("<dc:description>{}</dc:description>", escape_xml(&comic.description)));
+        }
+        for tag in &comic.tags {
+            metadata.push(format!("<dc:subject>{}</dc:subject>", escape_xml(tag)));
+        }
+        metadata.push(format!(r#"<dc:source>{}</dc:source>"#, escape_xml(&comic.url)));
+        metadata.push(format!(r#"<meta property="dcterms:modified">{}</meta>"#, utc_timestamp()));
+        metadata.push(String::from(r#"<meta property="rendition:layout">pre-paginated</meta>"#));
+        metadata.push(String::from(r#"<meta property="rendition:orientation">auto</meta>"#));
+        metadata.push(String::from(r#"<meta property="rendition:spread">landscape</meta>"#));
+
+        let mut manifest = vec![];
+        manifest.push(String::from(
+            r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#,
+        ));
+        let cover_id = match &cover {
+            Some((href, mime)) => {
+                manifest.push(format!(
+                    r#"<item id="cover" href="{}" media-type="{}" properties="cover-image"/>"#,
+                    href, mime
+                ));
+                String::from("cover")
+            }
+            None => format!("img_{}", pages[0].id),
+        };
+        metadata.push(format!(r#"<meta name="cover" content="{}"/>"#, cover_id));
+        let mut spine = vec![];
+        for (i, page) in pages.iter().enumerate() {
+            let properties = if cover.is_none() && i == 0 {
+                r#" properties="cover-image""#
+            } else {
+                ""
+            };
+            manifest.push(format!(
+                r#"<item id="img_{id}" href="{href}" media-type="{mime}"{properties}/>"#,
+                id = page.id,
+                href = page.image_href,
+                mime = page.mime,
+                properties = properties
+            ));
+            manifest.push(format!(
+                r#"<item id="{id}" href="pages/{id}.xhtml" media-type="application/xhtml+xml"/>"#,
+                id = page.id
+            ));
+            // 横向的跨页图片单独占满整个视口
+            let spread = if page.width > page.height {
+                r#" properties="rendition:spread-none""#
+            } else {
+                ""
+            };
+            spine.push(format!(r#"<itemref idref="{}"{}/>"#, page.id, spread));
+        }
+        let direction = match self.direction {
+            Direction::LeftToRight => "ltr",
+            Direction::RightToLeft => "rtl",
+        };
+
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="uid" prefix="rendition: http://www.idpf.org/vocab/rendition/#">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    {metadata}
+  </metadata>
+  <manifest>
+    {manifest}
+  </manifest>
+  <spine page-progression-direction="{direction}">
+    {spine}
+  </spine>
+</package>
+"#,
+            metadata = metadata.join("\n    "),
+            manifest = manifest.join("\n    "),
+            direction = direction,
+            spine = spine.join("\n    ")
+        )
+    }
+}
+
+static CONTAINER_XML: &'static str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+fn page_xhtml(title: &str, image_href: &str, width: u32, height: u32) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+  <title>{title}</title>
+  <meta name="viewport" content="width={width}, height={height}"/>
+  <style>html, body {{ margin: 0; padding: 0; }} img {{ display: block; width: 100%; height: 100%; object-fit: contain; }}</style>
+</head>
+<body>
+  <img src="../{href}" alt=""/>
+</body>
+</html>
+"#,
+        title = escape_xml(title),
+        width = width,
+        height = height,
+        href = image_href
+    )
+}
+
+#[test]
+fn test_write_epub() {
+    use image::{DynamicImage, ImageOutputFormat, RgbImage};
+    use std::io::Read;
+
+    let root = std::env::temp_dir().join("mikack-test-epub");
+    let _ = fs::remove_dir_all(&root);
+    let mut comic = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    for i in 1..=3 {
+        let chapter = Chapter::new(format!("第{}话", i), format!("https://www.manhuagui.com/comic/2863/{}.html", i), i);
+        let dir = download::chapter_dir(&root, &chapter);
+        fs::create_dir_all(&dir).unwrap();
+        let mut bytes = vec![];
+        DynamicImage::ImageRgb8(RgbImage::new(60, 80))
+            .write_to(&mut bytes, ImageOutputFormat::Png)
+            .unwrap();
+        fs::write(dir.join("1.png"), &bytes).unwrap();
+        fs::write(dir.join("2.png"), &bytes).unwrap();
+        comic.push_chapter(chapter);
+    }
+
+    let output = root.join("out.epub");
+    Epub::new(&comic)
+        .direction(Direction::RightToLeft)
+        .write_range(&root, 1..=2, &output)
+        .unwrap();
+    let mut archive = zip::ZipArchive::new(fs::File::open(&output).unwrap()).unwrap();
+    assert_eq!("mimetype", archive.by_index(0).unwrap().name());
+    let mut opf = String::new();
+    archive.by_name("OEBPS/content.opf").unwrap().read_to_string(&mut opf).unwrap();
+    assert!(opf.contains(r#"page-progression-direction="rtl""#));
+    assert!(opf.contains("<dc:title>食戟之灵</dc:title>"));
+    assert_eq!(4, opf.matches("<itemref").count());
+    let mut nav = String::new();
+    archive.by_name("OEBPS/nav.xhtml").unwrap().read_to_string(&mut nav).unwrap();
+    assert!(nav.contains(r#"<a href="pages/c0001_p0001.xhtml">第2话</a>"#));
+    assert!(archive.by_name("OEBPS/images/c0002_p0002.png").is_ok());
+    let _ = fs::remove_dir_all(&root);
+}
@@ -0,0 +1,412 @@
+use super::*;
+use super::transform::{load_pages, Transform};
+use crate::download;
+use flate2::{write::ZlibEncoder, Compression};
+use image::{codecs::jpeg::JpegDecoder, ColorType, ImageDecoder};
+use std::io::{Cursor, Write};
+
+/// 将已下载的章节导出为 PDF，每页一张图片，每个章节一个书签。
+pub struct Pdf<'a> {
+    comic: &'a Comic,
+    direction: Direction,
+    transform: Transform,
+}
+
+impl<'a> Pdf<'a> {
+    pub fn new(comic: &'a Comic) -> Self {
+        Self {
+            comic,
+            direction: Default::default(),
+            transform: Default::default(),
+        }
+    }
+
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// 导出前对页面图片的变换。
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn write_chapter(&self, chapter: &Chapter, dir: &Path, output: &Path) -> Result<()> {
+        self.write(&[(chapter, dir)], output)
+    }
+
+    /// 将整部漫画导出为一个 PDF，章节目录遵循 `download::chapter_dir` 的布局，未下载的章节会被跳过。
+    pub fn write_comic(&self, comic_dir: &Path, output: &Path) -> Result<()> {
+        let dirs = self
+            .comic
+            .chapters
+            .iter()
+            .map(|chapter| (chapter, download::chapter_dir(comic_dir, chapter)))
+            .filter(|(_, dir)| dir.is_dir())
+            .collect::<Vec<_>>();
+        let chapters = dirs
+            .iter()
+            .map(|(chapter, dir)| (*chapter, dir.as_path()))
+            .collect::<Vec<_>>();
+
+        self.write(&chapters, output)
+    }
+
+    pub fn write(&self, chapters: &[(&Chapter, &Path)], output: &Path) -> Result<()> {
+        if chapters.is_empty() {
+            return Err(err_msg("No chapters to export"));
+        }
+        let mut doc = Document::new();
+        let catalog_id = doc.reserve();
+        let pages_id = doc.reserve();
+        let outlines_id = doc.reserve();
+
+        let mut page_ids = vec![];
+        let mut bookmarks = vec![];
+        for (chapter, dir) in chapters {
+            let images = load_pages(chapter, dir, &self.transform, self.direction)?;
+            bookmarks.push((chapter.title.clone(), page_ids.len()));
+            for image in images {
+                let image = PdfImage::load(&image.bytes, &image.mime)?;
+                let image_id = doc.add(image.to_object());
+                let (width, height) = (image.width, image.height);
+                let content = format!("q {} 0 0 {} 0 0 cm /Im0 Do Q", width, height);
+                let content_id = doc.add(stream("", content.as_bytes()));
+                let page_id = doc.add(
+                    format!(
+                        "<< /Type /Page /Parent {} /MediaBox [0 0 {} {}] /Resources << /XObject << /Im0 {} >> >> /Contents {} >>",
+                        reference(pages_id),
+                        width,
+                        height,
+                        reference(image_id),
+                        reference(content_id)
+                    )
+                    .into_bytes(),
+                );
+                page_ids.push(page_id);
+            }
+        }
+
+        let kids = page_ids.iter().map(|id| reference(*id)).collect::<Vec<_>>();
+        doc.set(
+            pages_id,
+            format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), page_ids.len()).into_bytes(),
+        );
+
+        // 书签
+        let item_ids = bookmarks.iter().map(|_| doc.reserve()).collect::<Vec<_>>();
+        for (i, (title, page_index)) in bookmarks.iter().enumerate() {
+            let mut item = format!(
+                "<< /Title {} /Parent {} /Dest [{} /Fit]",
+                text_string(title),
+                reference(outlines_id),
+                reference(page_ids[*page_index])
+            );
+            if i > 0 {
+                item.push_str(&format!(" /Prev {}", reference(item_ids[i - 1])));
+            }
+            if i + 1 < item_ids.len() {
+                item.push_str(&format!(" /Next {}", reference(item_ids[i + 1])));
+            }
+            item.push_str(" >>");
+            doc.set(item_ids[i], item.into_bytes());
+        }
+        doc.set(
+            outlines_id,
+            format!(
+                "<< /Type /Outlines /First {} /Last {} /Count {} >>",
+                reference(item_ids[0]),
+                reference(item_ids[item_ids.len() - 1]),
+                item_ids.len()
+            )
+            .into_bytes(),
+        );
+
+        let direction = match self.direction {
+            Direction::LeftToRight => "/L2R",
+            Direction::RightToLeft => "/R2L",
+        };
+        doc.set(
+            catalog_id,
+            format!(
+                "<< /Type /Catalog /Pages {} /Outlines {} /PageMode /UseOutlines /ViewerPreferences << /Direction {} >> >>",
+                reference(pages_id),
+                reference(outlines_id),
+                direction
+            )
+            .into_bytes(),
+        );
+        let info_id = doc.add(self.info().into_bytes());
+
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(output, doc.finish(catalog_id, info_id))?;
+
+        Ok(())
+    }
+
+    fn info(&self) -> String {
+        let comic = self.comic;
+        let mut info = format!("<< /Title {} /Creator {}", text_string(&comic.title), text_string("mikack"));
+        if !comic.author.is_empty() {
+            info.push_str(&format!(" /Author {}", text_string(&comic.author)));
+        }
+        if !comic.description.is_empty() {
+            info.push_str(&format!(" /Subject {}", text_string(&comic.description)));
+        }
+        if !comic.tags.is_empty() {
+            info.push_str(&format!(" /Keywords {}", text_string(&comic.tags.join(", "))));
+        }
+        // `2020-03-01T08:00:00Z` => `D:20200301080000Z`
+        let date = utc_timestamp().replace(&['-', ':', 'T'][..], "");
+        info.push_str(&format!(" /CreationDate (D:{}) >>", date));
+
+        info
+    }
+}
+
+struct PdfImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    decode: Option<&'static str>,
+    filter: &'static str,
+    data: Vec<u8>,
+}
+
+impl PdfImage {
+    /// JPEG 直接嵌入，其它格式（PNG/WebP/GIF 首帧等）解码后以 Flate 压缩嵌入。
+    fn load(bytes: &[u8], mime: &str) -> Result<Self> {
+        let mime = mime::sniff(bytes).unwrap_or(mime);
+        if mime == "image/jpeg" {
+            let decoder = JpegDecoder::new(Cursor::new(bytes))?;
+            let (width, height) = decoder.dimensions();
+            // 解码器会把 CMYK 转换为 RGB，颜色空间需按文件中的分量数确定
+            let (components, adobe) = jpeg_components(bytes);
+            let (color_space, decode) = match (components, decoder.color_type()) {
+                // Adobe 生成的 CMYK JPEG 分量是反相存储的
+                (Some(4), _) if adobe => ("/DeviceCMYK", Some("[1 0 1 0 1 0 1 0]")),
+                (Some(4), _) => ("/DeviceCMYK", None),
+                (_, ColorType::L8) => ("/DeviceGray", None),
+                _ => ("/DeviceRGB", None),
+            };
+            return Ok(Self {
+                width,
+                height,
+                color_space,
+                decode,
+                filter: "/DCTDecode",
+                data: bytes.to_vec(),
+            });
+        }
+
+        let image = image::load_from_memory(bytes)?.to_rgba8();
+        let (width, height) = image.dimensions();
+        // 透明部分以白色背景合成
+        let mut rgb = Vec::with_capacity((width * height * 3) as usize);
+        for pixel in image.pixels() {
+            let alpha = pixel[3] as u32;
+            for channel in &pixel.0[..3] {
+                rgb.push(((*channel as u32 * alpha + 255 * (255 - alpha)) / 255) as u8);
+            }
+        }
+        let mut encoder = ZlibEncoder::new(vec![], Compression::default());
+        encoder.write_all(&rgb)?;
+
+        Ok(Self {
+            width,
+            height,
+            color_space: "/DeviceRGB",
+            decode: None,
+            filter: "/FlateDecode",
+            data: encoder.finish()?,
+        })
+    }
+
+    fn to_object(&self) -> Vec<u8> {
+        let mut dict = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace {} /BitsPerComponent 8 /Filter {}",
+            self.width, self.height, self.color_space, self.filter
+        );
+        if let Some(decode) = self.decode {
+            dict.push_str(&format!(" /Decode {}", decode));
+        }
+        stream(&dict, &self.data)
+    }
+}
+
+/// 读取 JPEG 帧头中的分量数，以及是否带有 Adobe APP14 段。
+fn jpeg_components(bytes: &[u8]) -> (Option<u8>, bool) {
+    let mut adobe = false;
+    let mut pos = 2;
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        let length = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let segment = &bytes[pos + 4..(pos + 2 + length).min(bytes.len())];
+        match marker {
+            0xEE if segment.starts_with(b"Adobe") => adobe = true,
+            // SOF0-SOF15，除去 DHT(C4)、JPG(C8) 和 DAC(CC)
+            0xC0..=0xCF if marker != 0xC4 && marker != 0xC8 && marker != 0xCC => {
+                return (segment.get(5).copied(), adobe);
+            }
+            0xDA | 0xD9 => break,
+            _ => (),
+        }
+        pos += 2 + length;
+    }
+    (None, adobe)
+}
+
+struct Document {
+    objects: Vec<Option<Vec<u8>>>,
+}
+
+impl Document {
+    fn new() -> Self {
+        Self { objects: vec![] }
+    }
+
+    // 对象编号从 1 开始
+    fn reserve(&mut self) -> usize {
+        self.objects.push(None);
+        self.objects.len()
+    }
+
+    fn set(&mut self, id: usize, object: Vec<u8>) {
+        self.objects[id - 1] = Some(object);
+    }
+
+    fn add(&mut self, object: Vec<u8>) -> usize {
+        let id = self.reserve();
+        self.set(id, object);
+        id
+    }
+
+    fn finish(self, root: usize, info: usize) -> Vec<u8> {
+        let mut buf = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = vec![];
+        for (i, object) in self.objects.iter().enumerate() {
+            offsets.push(buf.len());
+            buf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+            buf.extend_from_slice(object.as_ref().map(|o| &o[..]).unwrap_or(b"null"));
+            buf.extend_from_slice(b"\nendobj\n");
+        }
+        let xref_offset = buf.len();
+        buf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes());
+        for offset in offsets {
+            buf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+        }
+        buf.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root {} /Info {} >>\nstartxref\n{}\n%%EOF\n",
+                self.objects.len() + 1,
+                reference(root),
+                reference(info),
+                xref_offset
+            )
+            .as_bytes(),
+        );
+
+        buf
+    }
+}
+
+fn reference(id: usize) -> String {
+    format!("{} 0 R", id)
+}
+
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<< {} /Length {} >>\nstream\n", dict, data.len()).into_bytes();
+    object.extend_from_slice(data);
+    object.extend_from_slice(b"\nendstream");
+    object
+}
+
+// 以 UTF-16BE（带 BOM）十六进制字符串表示文本，兼容中日文标题
+fn text_string(text: &str) -> String {
+    let mut hex = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        hex.push_str(&format!("{:04X}", unit));
+    }
+    hex.push('>');
+    hex
+}
+
+#[test]
+fn test_write_pdf() {
+    use image::{DynamicImage, ImageOutputFormat, RgbImage, RgbaImage};
+
+    let dir = std::env::temp_dir().join("mikack-test-pdf");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let mut jpeg = vec![];
+    DynamicImage::ImageRgb8(RgbImage::new(40, 60))
+        .write_to(&mut jpeg, ImageOutputFormat::Jpeg(80))
+        .unwrap();
+    let mut png = vec![];
+    DynamicImage::ImageRgba8(RgbaImage::new(30, 20))
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .unwrap();
+    fs::write(dir.join("1.jpg"), &jpeg).unwrap();
+    fs::write(dir.join("2.png"), &png).unwrap();
+
+    let mut comic = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    comic.author = String::from("附田祐斗");
+    let chapter = Chapter::new("第1话", "https://www.manhuagui.com/comic/2863/1.html", 1);
+    let output = dir.join("out.pdf");
+    Pdf::new(&comic).write_chapter(&chapter, &dir, &output).unwrap();
+
+    let pdf = fs::read(&output).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.ends_with("%%EOF\n"));
+    assert_eq!(2, text.matches("/Type /Page ").count());
+    assert!(text.contains("/MediaBox [0 0 40 60]"));
+    assert!(text.contains("/MediaBox [0 0 30 20]"));
+    assert!(text.contains("/Filter /DCTDecode"));
+    assert!(text.contains("/Filter /FlateDecode"));
+    assert!(text.contains(&format!("/Title {}", text_string("第1话"))));
+    // 交叉引用表中的偏移量指向对象开头
+    let xref = text.rfind("startxref\n").unwrap();
+    let xref_offset = text[xref + 10..].lines().next().unwrap().parse::<usize>().unwrap();
+    assert!(pdf[xref_offset..].starts_with(b"xref"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_load_cmyk_jpeg() {
+    use image::{DynamicImage, ImageOutputFormat, RgbImage};
+
+    let mut rgb = vec![];
+    DynamicImage::ImageRgb8(RgbImage::new(40, 60))
+        .write_to(&mut rgb, ImageOutputFormat::Jpeg(80))
+        .unwrap();
+    let image = PdfImage::load(&rgb, "image/jpeg").unwrap();
+    assert_eq!("/DeviceRGB", image.color_space);
+    assert_eq!(None, image.decode);
+
+    // 带 Adobe APP14 段的 4 分量 JPEG（帧头之后的数据不影响颜色空间的判断）
+    let mut cmyk = vec![0xFF, 0xD8];
+    cmyk.extend(&[0xFF, 0xEE, 0x00, 0x0E]);
+    cmyk.extend(b"Adobe\x00\x64\x00\x00\x00\x00\x00");
+    cmyk.extend(&[0xFF, 0xC0, 0x00, 0x14, 0x08, 0x00, 0x3C, 0x00, 0x28, 0x04]);
+    for id in 1..=4 {
+        cmyk.extend(&[id, 0x11, 0x00]);
+    }
+    let pos = rgb.windows(2).position(|w| w == [0xFF, 0xDA]).unwrap();
+    cmyk.extend(&rgb[pos..]);
+    let image = PdfImage::load(&cmyk, "image/jpeg").unwrap();
+    assert_eq!((40, 60), (image.width, image.height));
+    assert_eq!("/DeviceCMYK", image.color_space);
+    let object = String::from_utf8_lossy(&image.to_object()).to_string();
+    assert!(object.contains("/ColorSpace /DeviceCMYK"));
+    assert!(object.contains("/Decode [1 0 1 0 1 0 1 0]"));
+
+    // 没有 Adobe 段时不反相
+    let plain = [&cmyk[..2], &cmyk[18..]].concat();
+    let image = PdfImage::load(&plain, "image/jpeg").unwrap();
+    assert_eq!("/DeviceCMYK", image.color_space);
+    assert_eq!(None, image.decode);
+}
@@ -0,0 +1,301 @@
+use super::*;
+use crate::processing::{content_bounds, Format};
+use image::{imageops, imageops::FilterType, DynamicImage, GenericImageView, ImageOutputFormat, RgbImage};
+use std::io::Cursor;
+
+/// 导出时对页面图片的变换（拆分跨页、裁剪白边、缩放），默认不做任何变换。
+#[derive(Debug, Clone)]
+pub struct Transform {
+    split_spreads: bool,
+    crop_margins: Option<u8>,
+    resize: Option<(u32, u32)>,
+    strip: Option<(u32, u32)>,
+    format: Format,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            split_spreads: false,
+            crop_margins: None,
+            resize: None,
+            strip: None,
+            format: Format::Jpeg(90),
+        }
+    }
+}
+
+impl Transform {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// 将横向的跨页图片拆分为两页，拆分后的顺序遵循阅读方向。
+    pub fn split_spreads(mut self, split_spreads: bool) -> Self {
+        self.split_spreads = split_spreads;
+        self
+    }
+
+    /// 裁剪四周颜色一致的边缘，`tolerance` 为允许的颜色误差（0-255）。
+    pub fn crop_margins(mut self, tolerance: u8) -> Self {
+        self.crop_margins = Some(tolerance);
+        self
+    }
+
+    /// 等比缩小到不超过目标设备的分辨率，不会放大。
+    pub fn resize(mut self, width: u32, height: u32) -> Self {
+        self.resize = Some((width, height));
+        self
+    }
+
+    /// 将长条漫画（webtoon）的切片拼接为一整条，缩放到 `width` 后在空白间隙处重新切分为不超过 `height` 的页面。
+    ///
+    /// 开启后会忽略其它变换。
+    pub fn strip(mut self, width: u32, height: u32) -> Self {
+        self.strip = Some((width, height));
+        self
+    }
+
+    /// 变换后图片的编码格式，默认为 JPEG（质量 90）。
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn is_identity(&self) -> bool {
+        !self.split_spreads && self.crop_margins.is_none() && self.resize.is_none() && self.strip.is_none()
+    }
+
+    /// 变换单个页面，可能得到多个页面（拆分跨页时）。
+    pub fn apply(&self, page: PageImage, direction: Direction) -> Result<Vec<PageImage>> {
+        if self.is_identity() {
+            return Ok(vec![page]);
+        }
+        let mut image = image::load_from_memory(&page.bytes)?;
+        if let Some(tolerance) = self.crop_margins {
+            image = crop(&image, tolerance);
+        }
+        let mut images = if self.split_spreads && image.width() > image.height() {
+            let (width, height) = (image.width() / 2, image.height());
+            let left = image.crop_imm(0, 0, width, height);
+            let right = image.crop_imm(width, 0, image.width() - width, height);
+            match direction {
+                Direction::LeftToRight => vec![left, right],
+                Direction::RightToLeft => vec![right, left],
+            }
+        } else {
+            vec![image]
+        };
+        if self.split_spreads && images.len() > 1 {
+            if let Some(tolerance) = self.crop_margins {
+                images = images.iter().map(|image| crop(image, tolerance)).collect();
+            }
+        }
+        if let Some((width, height)) = self.resize {
+            images = images
+                .into_iter()
+                .map(|image| fit(image, width, height))
+                .collect();
+        }
+
+        images
+            .iter()
+            .map(|image| PageImage::encode(image, self.format))
+            .collect()
+    }
+}
+
+fn crop(image: &DynamicImage, tolerance: u8) -> DynamicImage {
+    let (x, y, width, height) = content_bounds(image, tolerance);
+    image.crop_imm(x, y, width, height)
+}
+
+/// 等比缩小到 `width` x `height` 以内。
+pub fn fit(image: DynamicImage, width: u32, height: u32) -> DynamicImage {
+    if image.width() <= width && image.height() <= height {
+        return image;
+    }
+    image.resize(width, height, FilterType::Lanczos3)
+}
+
+/// 导出时在内存中的页面图片。
+#[derive(Debug, Clone)]
+pub struct PageImage {
+    pub bytes: Vec<u8>,
+    pub mime: String,
+}
+
+impl PageImage {
+    pub fn read(file: &PageFile) -> Result<Self> {
+        let bytes = fs::read(&file.path)?;
+        let mime = mime::sniff(&bytes).unwrap_or(&file.mime).to_string();
+        Ok(Self { bytes, mime })
+    }
+
+    pub fn encode(image: &DynamicImage, format: Format) -> Result<Self> {
+        let mut bytes = vec![];
+        match format {
+            Format::Jpeg(quality) => DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_to(&mut bytes, ImageOutputFormat::Jpeg(quality))?,
+            Format::Png => image.write_to(&mut bytes, ImageOutputFormat::Png)?,
+        };
+        Ok(Self {
+            bytes,
+            mime: format.mime().to_string(),
+        })
+    }
+
+    pub fn extension(&self) -> &str {
+        mime::to_extension(&self.mime).unwrap_or("bin")
+    }
+
+    pub fn dimensions(&self) -> Result<(u32, u32)> {
+        Ok(image::io::Reader::new(Cursor::new(&self.bytes))
+            .with_guessed_format()?
+            .into_dimensions()?)
+    }
+}
+
+/// 将切片按顺序纵向拼接，每个切片等比缩放到 `width`。
+pub fn stitch(images: &[DynamicImage], width: u32) -> RgbImage {
+    let images = images
+        .iter()
+        .filter(|image| image.width() > 0)
+        .map(|image| {
+            let height = (image.height() as u64 * width as u64 / image.width() as u64).max(1) as u32;
+            if image.width() == width {
+                image.to_rgb8()
+            } else {
+                image.resize_exact(width, height, FilterType::Lanczos3).to_rgb8()
+            }
+        })
+        .collect::<Vec<_>>();
+    let height = images.iter().map(|image| image.height()).sum();
+    let mut strip = RgbImage::new(width, height);
+    let mut y = 0;
+    for image in images {
+        imageops::replace(&mut strip, &image, 0, y);
+        y += image.height();
+    }
+
+    strip
+}
+
+/// 判断空白间隙时允许的颜色误差。
+const GUTTER_TOLERANCE: i32 = 8;
+
+/// 计算长条的切分位置 `(y, height)`。
+///
+/// 每页优先在下半部分中最靠下的空白行（颜色一致的行）处切分，找不到时按 `height` 硬切。
+pub fn split_strip(strip: &RgbImage, height: u32) -> Vec<(u32, u32)> {
+    let gray = DynamicImage::ImageRgb8(strip.clone()).to_luma8();
+    let is_gutter = |y: u32| {
+        let first = gray.get_pixel(0, y)[0] as i32;
+        (0..gray.width()).all(|x| (gray.get_pixel(x, y)[0] as i32 - first).abs() <= GUTTER_TOLERANCE)
+    };
+    let height = height.max(1);
+    let mut cuts = vec![];
+    let mut top = 0;
+    while top < strip.height() {
+        let limit = top + height;
+        if limit >= strip.height() {
+            cuts.push((top, strip.height() - top));
+            break;
+        }
+        let bottom = (top + height / 2..limit)
+            .rev()
+            .find(|y| is_gutter(*y))
+            .map(|y| y + 1)
+            .unwrap_or(limit);
+        cuts.push((top, bottom - top));
+        top = bottom;
+    }
+
+    cuts
+}
+
+fn load_strip(files: &[PageFile], transform: &Transform, width: u32, height: u32) -> Result<Vec<PageImage>> {
+    let mut images = vec![];
+    for file in files {
+        images.push(image::load_from_memory(&fs::read(&file.path)?)?);
+    }
+    let strip = stitch(&images, width);
+    split_strip(&strip, height)
+        .into_iter()
+        .map(|(y, height)| {
+            let page = imageops::crop_imm(&strip, 0, y, width, height).to_image();
+            PageImage::encode(&DynamicImage::ImageRgb8(page), transform.format)
+        })
+        .collect()
+}
+
+/// 读取章节目录中的页面并应用变换。
+pub fn load_pages(chapter: &Chapter, dir: &Path, transform: &Transform, direction: Direction) -> Result<Vec<PageImage>> {
+    let files = page_files(chapter, dir)?;
+    if let Some((width, height)) = transform.strip {
+        return load_strip(&files, transform, width, height);
+    }
+    let mut pages = vec![];
+    for file in files {
+        pages.append(&mut transform.apply(PageImage::read(&file)?, direction)?);
+    }
+
+    Ok(pages)
+}
+
+#[test]
+fn test_transform() {
+    use image::{Rgb, RgbImage};
+
+    // 白边包围的跨页：左半部分为黑色，右半部分为灰色
+    let spread = RgbImage::from_fn(220, 120, |x, y| {
+        if !(10..210).contains(&x) || !(10..110).contains(&y) {
+            Rgb([255, 255, 255])
+        } else if x < 110 {
+            Rgb([0, 0, 0])
+        } else {
+            Rgb([128, 128, 128])
+        }
+    });
+    let page = PageImage::encode(&DynamicImage::ImageRgb8(spread), Format::Png).unwrap();
+
+    assert_eq!(1, Transform::new().apply(page.clone(), Direction::LeftToRight).unwrap().len());
+    let transform = Transform::new().split_spreads(true).crop_margins(16).format(Format::Png);
+    let pages = transform.apply(page.clone(), Direction::RightToLeft).unwrap();
+    assert_eq!(2, pages.len());
+    assert_eq!((100, 100), pages[0].dimensions().unwrap());
+    // 从右到左阅读时右半部分在前
+    let first = image::load_from_memory(&pages[0].bytes).unwrap().to_rgb8();
+    assert_eq!(&Rgb([128, 128, 128]), first.get_pixel(50, 50));
+
+    let transform = Transform::new().split_spreads(true).resize(60, 60);
+    let pages = transform.apply(page, Direction::LeftToRight).unwrap();
+    assert_eq!("image/jpeg", pages[0].mime);
+    assert_eq!((55, 60), pages[0].dimensions().unwrap());
+}
+
+#[test]
+fn test_strip() {
+    use image::Rgb;
+
+    // 两个切片缩放到宽度 50 后分别为 50x100 和 50x50，第一个在 y=60..70 处有白色间隙
+    let first = RgbImage::from_fn(100, 200, |x, y| {
+        if (120..140).contains(&y) {
+            Rgb([255, 255, 255])
+        } else {
+            Rgb([(x * 2) as u8, 0, 0])
+        }
+    });
+    let second = RgbImage::from_fn(50, 50, |x, _| Rgb([x as u8, 0, 0]));
+    let strip = stitch(
+        &[DynamicImage::ImageRgb8(first), DynamicImage::ImageRgb8(second)],
+        50,
+    );
+    assert_eq!((50, 150), strip.dimensions());
+
+    let cuts = split_strip(&strip, 80);
+    assert_eq!(150, cuts.iter().map(|(_, h)| h).sum::<u32>());
+    // 第一页在间隙处切分，而不是在 80 处硬切
+    assert!(cuts[0].1 > 60 && cuts[0].1 <= 70);
+    assert!(cuts.iter().all(|(_, h)| *h <= 80));
+}
@@ -1,14 +1,21 @@
+use crate::detection::{self, Signature};
+use crate::processing::{self, ProcessorObject};
+use crate::progress::{silent, Event, Listener};
 use crate::{error::*, models::*};
 use duang::duang;
 use encoding_rs::*;
+use percent_encoding::{percent_encode, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use quick_js::{Context, JsValue};
 use regex::Regex;
 use std::any::Any;
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::collections::HashMap;
+use std::time::Instant;
 use std::vec::Vec;
 
 pub use crate::helper::{document_ext::*, grouped_items::*, *};
+pub use crate::progress::Total;
 
 macro_rules! def_bool_status {
     ( $(:$name:ident),* ) => {
@@ -44,7 +51,8 @@ type Status = HashMap<&'static str, Box<dyn Any + Send + Sync>>;
 
 #[allow(unused_variables)]
 pub trait Extractor {
-    def_bool_status![:usable, :searchable, :pageable, :pageable_search, :https];
+    // expiring：页面地址带有时效签名，过期后（响应 403/410）可重新解析
+    def_bool_status![:usable, :searchable, :pageable, :pageable_search, :https, :expiring];
 
     def_status_access!(&str, favicon);
 
@@ -63,12 +71,28 @@ pub trait Extractor {
     fn pages_iter<'a>(&'a self, chapter: &'a mut Chapter) -> Result<ChapterPages> {
         Ok(ChapterPages::new(
             chapter,
-            0,
+            Total::Known(0),
             vec![],
             Box::new(|_| Ok(vec![])),
         ))
     }
 
+    /// 解析页面并通过 `listener` 报告进度。
+    fn pages_iter_with<'a>(&'a self, chapter: &'a mut Chapter, listener: Listener) -> Result<ChapterPages> {
+        let url = chapter.url.clone();
+        listener(&Event::Discovering { url: url.clone() });
+        match self.pages_iter(chapter) {
+            Ok(pages) => Ok(pages.listen(listener)),
+            Err(e) => {
+                listener(&Event::ChapterFailed {
+                    url,
+                    error: e.to_string(),
+                });
+                Err(e)
+            }
+        }
+    }
+
     fn fetch_pages(&self, chapter: &mut Chapter) -> Result<()> {
         self.pages_iter(chapter)?.for_each(drop);
         Ok(())
@@ -88,19 +112,242 @@ pub trait Extractor {
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
         self.search(keywords)
     }
+
+    /// 构建下载页面图片的请求，需要额外请求头、Cookie 或签名参数的来源可覆盖此方法。
+    ///
+    /// `client` 由所有来源共用并保留 Cookie，默认附加章节的 `page_headers`。
+    fn page_request(&self, client: &Client, chapter: &Chapter, page: &Page) -> Result<RequestBuilder> {
+        let mut builder = client
+            .get(&page.address)
+            .header(USER_AGENT, DEFAULT_USER_AGENT);
+        for (key, value) in &chapter.page_headers {
+            builder = builder.header(key.as_str(), value.as_str());
+        }
+
+        Ok(builder)
+    }
+
+    /// 下载页面图片数据，返回的页面已根据实际内容修正了类型和文件名。
+    ///
+    /// 地址带有时效签名的来源（见 `is_expiring`）在地址过期（响应 403/410）时会自动重新解析该页并重试，
+    /// 此时返回的页面包含新地址。
+    fn download_page(&self, chapter: &Chapter, page: &Page) -> Result<PageData> {
+        self.download_page_with(chapter, page, &silent())
+    }
+
+    /// 同 `download_page`，并通过 `listener` 报告重试、完成和失败事件。
+    fn download_page_with(&self, chapter: &Chapter, page: &Page, listener: &Listener) -> Result<PageData> {
+        let n = chapter
+            .pages
+            .iter()
+            .position(|p| p.address == page.address)
+            .map(|i| i + 1)
+            .unwrap_or(page.n);
+        let result = download_page_data(self, chapter, page, n, listener);
+        match &result {
+            Ok(data) => listener(&Event::PageDownloaded {
+                n,
+                size: data.bytes.len(),
+            }),
+            Err(e) => listener(&Event::Failed {
+                n,
+                error: e.to_string(),
+            }),
+        }
+
+        result
+    }
+
+    /// 来源要求的后处理器（例如还原切片），会在 `download_page` 中自动执行。
+    fn processors(&self) -> Vec<ProcessorObject> {
+        vec![]
+    }
+
+    /// 来源已知的占位图片特征（图片已删除、防盗链提示等），下载器会将匹配的页面视为失败。
+    fn placeholders(&self) -> Vec<Signature> {
+        vec![]
+    }
+
+    /// 重新解析第 `n` 页（从 1 开始）的地址，用于更新已过期的地址。
+    fn refresh_page(&self, chapter: &mut Chapter, n: usize) -> Result<()> {
+        if n == 0 || n > chapter.pages.len() {
+            return Err(err_msg(format!("Page not found: {}", n)));
+        }
+        let default_headers = Chapter::make_headers(&chapter.url);
+        let mut fresh = Chapter::from_url(&chapter.url);
+        let address = self
+            .pages_iter(&mut fresh)?
+            .nth(n - 1)
+            .ok_or(err_msg(format!("Page not found after refreshing: {}", n)))??
+            .address;
+        // 只更新地址，保留已确定的类型和文件名
+        chapter.pages[n - 1].address = address;
+        // 仅在解析过程中调整过请求头时才覆盖（部分来源会清空请求头）
+        if fresh.page_headers != default_headers {
+            chapter.page_headers = fresh.page_headers;
+        }
+
+        Ok(())
+    }
+
+    /// 重新解析全部页面。
+    fn refresh_pages(&self, chapter: &mut Chapter) -> Result<()> {
+        chapter.pages.clear();
+        for page in self.pages_iter(chapter)? {
+            page?;
+        }
+
+        Ok(())
+    }
+}
+
+fn download_page_data<E: Extractor + ?Sized>(
+    extr: &E,
+    chapter: &Chapter,
+    page: &Page,
+    n: usize,
+    listener: &Listener,
+) -> Result<PageData> {
+    let data = if page.address.starts_with("data:") {
+        PageData::from_data_url(page)?
+    } else {
+        let client = &*PAGE_CLIENT;
+        let in_chapter = n > 0 && chapter.pages.get(n - 1).map(|p| p.address == page.address) == Some(true);
+        match fetch_page_data(extr, client, chapter, page, n, listener) {
+            Err(e) if is_expired(&e) && in_chapter => {
+                listener(&Event::Retrying {
+                    n,
+                    attempt: 1,
+                    error: e.to_string(),
+                });
+                let mut refreshed = chapter.clone();
+                extr.refresh_page(&mut refreshed, n)?;
+                fetch_page_data(extr, client, &refreshed, &refreshed.pages[n - 1], n, listener)?
+            }
+            r => r?,
+        }
+    };
+    if detection::is_placeholder(&extr.placeholders(), &data.page.address, &data.bytes) {
+        return Err(PageError::Placeholder {
+            address: data.page.address,
+        }
+        .into());
+    }
+
+    processing::run(&extr.processors(), data)
+}
+
+fn fetch_page_data<E: Extractor + ?Sized>(
+    extr: &E,
+    client: &Client,
+    chapter: &Chapter,
+    page: &Page,
+    n: usize,
+    listener: &Listener,
+) -> Result<PageData> {
+    let mut last_error = err_msg(format!("Page download failed: {}", page.address));
+    for attempt in 0..PAGE_DOWNLOAD_RETRIES {
+        if attempt > 0 {
+            listener(&Event::Retrying {
+                n,
+                attempt,
+                error: last_error.to_string(),
+            });
+            std::thread::sleep(std::time::Duration::from_millis(500 * attempt as u64));
+        }
+        let resp = match extr.page_request(client, chapter, page)?.send() {
+            Ok(resp) => resp,
+            Err(e) => {
+                last_error = e.into();
+                continue;
+            }
+        };
+        let status = resp.status();
+        if extr.is_expiring() && (status == StatusCode::FORBIDDEN || status == StatusCode::GONE) {
+            return Err(PageError::Expired {
+                status: status.as_u16(),
+                address: page.address.clone(),
+            }
+            .into());
+        }
+        if !status.is_success() {
+            last_error = err_msg(format!("Page download failed with status {}: {}", status, page.address));
+            if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                // 客户端错误重试无意义
+                break;
+            }
+            continue;
+        }
+        let content_type = resp
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        match resp.bytes() {
+            Ok(bytes) => return Ok(PageData::new(page, content_type.as_deref(), bytes.to_vec())),
+            Err(e) => last_error = e.into(),
+        }
+    }
+
+    Err(last_error)
+}
+
+fn is_expired(e: &failure::Error) -> bool {
+    matches!(e.downcast_ref::<PageError>(), Some(PageError::Expired { .. }))
+}
+
+/// 已下载的页面数据。
+#[derive(Debug, Clone)]
+pub struct PageData {
+    pub page: Page,
+    pub bytes: Vec<u8>,
+}
+
+impl PageData {
+    pub fn new(page: &Page, content_type: Option<&str>, bytes: Vec<u8>) -> Self {
+        let mut page = page.clone();
+        page.detect_mime(content_type, &bytes);
+        Self { page, bytes }
+    }
+
+    fn from_data_url(page: &Page) -> Result<Self> {
+        let (meta, data) = page.address[5..]
+            .split_at(page.address.find(',').ok_or(err_msg("Invalid data URL"))? - 5);
+        let data = &data[1..];
+        let bytes = if meta.ends_with(";base64") {
+            base64::decode(data)?
+        } else {
+            percent_encoding::percent_decode_str(data).collect::<Vec<u8>>()
+        };
+        let content_type = meta.split(';').next();
+
+        Ok(Self::new(page, content_type, bytes))
+    }
+}
+
+const PAGE_DOWNLOAD_RETRIES: usize = 3;
+
+lazy_static! {
+    /// 下载页面图片共用的客户端，复用连接并在请求之间保留 Cookie。
+    static ref PAGE_CLIENT: Client = Client::builder()
+        .danger_accept_invalid_certs(true)
+        .cookie_store(true)
+        .build()
+        .unwrap();
 }
 
 pub struct ChapterPages<'a> {
     pub chapter: &'a mut Chapter,
     pub current_page: usize,
     fetch: Box<dyn Fn(usize) -> Result<Vec<Page>>>,
-    pub total: i32,
+    pub total: Total,
+    listener: Option<Listener>,
 }
 
 impl<'a> ChapterPages<'a> {
     fn new(
         chapter: &'a mut Chapter,
-        total: i32,
+        total: Total,
         init_addresses: Vec<String>,
         fetch: Box<dyn Fn(usize) -> Result<Vec<Page>>>,
     ) -> Self {
@@ -112,18 +359,32 @@ impl<'a> ChapterPages<'a> {
             current_page: 0,
             fetch,
             total,
+            listener: None,
         }
     }
 
     fn full(chapter: &'a mut Chapter, addresses: Vec<String>) -> Self {
         Self::new(
             chapter,
-            addresses.len() as i32,
+            Total::Known(addresses.len()),
             addresses,
             Box::new(move |_| Ok(vec![])),
         )
     }
 
+    /// 监听页面解析事件。
+    pub fn listen(mut self, listener: Listener) -> Self {
+        listener(&Event::Discovered { total: self.total });
+        self.listener = Some(listener);
+        self
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(listener) = &self.listener {
+            listener(&event);
+        }
+    }
+
     #[allow(dead_code)]
     pub fn chapter_title_clone(&self) -> String {
         self.chapter.title.clone()
@@ -135,31 +396,53 @@ impl<'a> Iterator for ChapterPages<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         self.current_page += 1;
-        if self.total == 0 || (self.total > 0 && (self.total as usize) < self.current_page) {
-            return None;
+        if let Total::Known(total) = self.total {
+            if total < self.current_page {
+                return None;
+            }
         }
         let page_index = self.current_page - 1;
-        if ((self.chapter.pages.len() as i32) - 1) >= page_index as i32 {
-            return Some(Ok(self.chapter.pages[page_index].clone()));
+        if page_index < self.chapter.pages.len() {
+            let page = self.chapter.pages[page_index].clone();
+            self.emit(Event::PageResolved {
+                n: self.current_page,
+                total: self.total,
+            });
+            return Some(Ok(page));
         }
 
         match (self.fetch)(self.current_page) {
-            Ok(mut pages) => {
+            Ok(pages) => {
                 let count = pages.len();
-                self.chapter.pages.append(&mut pages);
+                // 各来源返回的页码不统一（从 0 或 1 开始），按在章节中的位置重新编号
+                for page in pages {
+                    let n = self.chapter.pages.len() + 1;
+                    let fname = Page::fname(&page.address, &n);
+                    self.chapter.pages.push(Page { n, fname, ..page });
+                }
                 let current_len = self.chapter.pages.len();
                 if count > 0 {
+                    self.emit(Event::PageResolved {
+                        n: self.current_page,
+                        total: self.total,
+                    });
                     Some(Ok(self.chapter.pages[current_len - count].clone()))
                 } else {
                     None
                 }
             }
-            Err(e) => Some(Err(e)),
+            Err(e) => {
+                self.emit(Event::Failed {
+                    n: self.current_page,
+                    error: e.to_string(),
+                });
+                Some(Err(e))
+            }
         }
     }
 }
 
-use reqwest::blocking::{Client, Response};
+use reqwest::blocking::{Client, RequestBuilder, Response};
 use scraper::{element_ref::ElementRef, Html, Selector};
 
 fn parse_selector(selector: &str) -> Result<Selector> {
@@ -167,13 +450,30 @@ fn parse_selector(selector: &str) -> Result<Selector> {
         .map_err(|_e| err_msg(format!("The selector '{}' parsing failed", selector)))?)
 }
 
-use reqwest::header::USER_AGENT;
+use reqwest::header::{CONTENT_TYPE, USER_AGENT};
+use reqwest::StatusCode;
 
 pub static DEFAULT_USER_AGENT: &'static str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/79.0.3945.130 Safari/537.36";
 
+thread_local! {
+    // 当前线程中 `get` 请求的截止时间，由搜索设置（见 `with_deadline`）
+    static DEADLINE: Cell<Option<Instant>> = Cell::new(None);
+}
+
+/// 在 `f` 执行期间为当前线程中 `get` 发出的请求设置截止时间，超过截止时间的请求会失败。
+pub(crate) fn with_deadline<R, F: FnOnce() -> R>(deadline: Instant, f: F) -> R {
+    let previous = DEADLINE.with(|d| d.replace(Some(deadline)));
+    let result = f();
+    DEADLINE.with(|d| d.set(previous));
+    result
+}
+
 pub fn get<T: reqwest::IntoUrl>(url: T) -> Result<Response> {
-    Ok(Client::builder()
-        .danger_accept_invalid_certs(true)
+    let mut builder = Client::builder().danger_accept_invalid_certs(true);
+    if let Some(deadline) = DEADLINE.with(|d| d.get()) {
+        builder = builder.timeout(deadline.saturating_duration_since(Instant::now()));
+    }
+    Ok(builder
         .build()?
         .get(url)
         .header(USER_AGENT, DEFAULT_USER_AGENT)
@@ -212,6 +512,22 @@ fn encode_text<'a>(text: &'a str, encoding: &'static Encoding) -> Result<Cow<'a,
     Ok(cow)
 }
 
+/// URL 中需要转义的字符：除 RFC 3986 中的非保留字符以外的全部字符。
+const KEYWORDS_ESCAPE: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// 转义 URL 中的搜索关键字（UTF-8），`&`、`#`、引号、空格和中日韩文字等均会被转义。
+///
+/// 除 ikkdm（GBK，见 `encode_keywords_with`）以及以表单提交的 pufei8、cartoonmad 外，其余来源的页面均以 UTF-8 解码，
+/// wuqimh、177mh、hhimm 等较早的网站也不例外，因此关键字同样按 UTF-8 转义。
+fn encode_keywords(keywords: &str) -> String {
+    utf8_percent_encode(keywords, KEYWORDS_ESCAPE).to_string()
+}
+
+/// 以网站使用的字符集（例如 GBK 或 Big5）转义 URL 中的搜索关键字。
+fn encode_keywords_with(keywords: &str, encoding: &'static Encoding) -> Result<String> {
+    Ok(percent_encode(&encode_text(keywords, encoding)?, KEYWORDS_ESCAPE).to_string())
+}
+
 type JsObject = HashMap<String, JsValue>;
 
 pub fn eval_as_obj(code: &str) -> Result<JsObject> {
@@ -495,6 +811,7 @@ trait AttachTo<T> {
     fn attach_to(self, target: &mut T);
     fn reversed_attach_to(self, target: &mut T);
     fn headers_clear(self) -> Self;
+    fn set_group(self, group: &str) -> Self;
 }
 
 impl AttachTo<Comic> for Vec<Chapter> {
@@ -516,6 +833,13 @@ impl AttachTo<Comic> for Vec<Chapter> {
         }
         self
     }
+
+    fn set_group(mut self, group: &str) -> Self {
+        for chapter in &mut self {
+            chapter.group = group.to_string();
+        }
+        self
+    }
 }
 
 macro_rules! def_regex2 {
@@ -569,6 +893,107 @@ fn test_eval_value() {
     assert_eq!(JsValue::Int(2), value);
 }
 
+#[test]
+fn test_chapter_pages_events() {
+    use std::sync::{Arc, Mutex};
+
+    let events = Arc::new(Mutex::new(vec![]));
+    let events_clone = events.clone();
+    let listener = crate::progress::listener(move |event| events_clone.lock().unwrap().push(event.clone()));
+    let mut chapter = Chapter::from_url("https://www.example.com/1.html");
+    let addresses = vec![String::from("https://www.example.com/1.jpg"), String::from("https://www.example.com/2.jpg")];
+    let pages = ChapterPages::full(&mut chapter, addresses).listen(listener);
+    assert_eq!(Total::Known(2), pages.total);
+    assert_eq!(2, pages.count());
+    let events = events.lock().unwrap();
+    assert_eq!(3, events.len());
+    match events[2] {
+        Event::PageResolved { n, total } => {
+            assert_eq!(2, n);
+            assert_eq!(Some(2), total.known());
+        }
+        _ => assert!(false),
+    }
+}
+
+#[test]
+fn test_chapter_pages_unknown_total() {
+    let mut chapter = Chapter::from_url("https://www.example.com/1.html");
+    let fetch = Box::new(|current_page: usize| {
+        if current_page > 3 {
+            return Ok(vec![]);
+        }
+        Ok(vec![Page::new(current_page - 1, format!("https://www.example.com/{}.jpg", current_page))])
+    });
+    let pages = ChapterPages::new(&mut chapter, Total::Unknown, vec![], fetch);
+    assert_eq!(None, pages.total.known());
+    assert_eq!(3, pages.count());
+    assert_eq!(
+        vec![(1, "1.jpg"), (2, "2.jpg"), (3, "3.jpg")],
+        chapter.pages.iter().map(|p| (p.n, p.fname.as_str())).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_download_data_url() {
+    let extr = get_extr("www.manhuagui.com").unwrap();
+    let chapter = Chapter::from_url("https://www.manhuagui.com/comic/2863/271796.html");
+    let page = Page::new(1, "data:image/gif;base64,R0lGODlhAQABAAAAACw=");
+    let data = extr.download_page(&chapter, &page).unwrap();
+    assert_eq!("image/gif", data.page.fmime);
+    assert_eq!("1.gif", data.page.fname);
+    assert_eq!(b"GIF89a"[..3], data.bytes[..3]);
+}
+
+#[test]
+fn test_page_request() {
+    let page = Page::new(1, "https://i.hamreus.com/ps3/s/1.jpg");
+    let chapter = Chapter::default();
+    let request = get_extr("www.manhuagui.com")
+        .unwrap()
+        .page_request(&PAGE_CLIENT, &chapter, &page)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!("https://www.manhuagui.com/", request.headers()["Referer"]);
+
+    let chapter = Chapter::from_url("https://www.manhuadb.com/manhua/147/1330_14552.html");
+    let request = get_extr("www.manhuadb.com")
+        .unwrap()
+        .page_request(&PAGE_CLIENT, &chapter, &page)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(chapter.url, request.headers()["Referer"]);
+}
+
+#[test]
+fn test_expiring() {
+    assert!(get_extr("www.manhuagui.com").unwrap().is_expiring());
+    assert!(!get_extr("www.manhuadb.com").unwrap().is_expiring());
+}
+
+#[test]
+fn test_processors() {
+    let names = |domain: &str| {
+        get_extr(domain)
+            .unwrap()
+            .processors()
+            .iter()
+            .map(|p| p.name().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(vec!["convert_format"], names("www.manhuagui.com"));
+    assert!(names("www.manhuadb.com").is_empty());
+}
+
+#[test]
+fn test_placeholders() {
+    let placeholders = get_extr("e-hentai.org").unwrap().placeholders();
+    assert!(detection::is_placeholder(&placeholders, "https://ehgt.org/g/509.gif", b""));
+    assert!(get_extr("www.manhuadb.com").unwrap().placeholders().is_empty());
+}
+
 #[test]
 fn test_eval_obj() {
     let code = r#"
@@ -820,6 +1245,14 @@ pub fn find_platforms(includes: Vec<Tag>, excludes: Vec<Tag>) -> HashMap<String,
         .collect::<HashMap<String, String>>()
 }
 
+#[test]
+fn test_encode_keywords() {
+    assert_eq!("a%26b%23c%20%22d%22-e.f", encode_keywords(r#"a&b#c "d"-e.f"#));
+    assert_eq!("%E9%A3%9F%E6%88%9F", encode_keywords("食戟"));
+    assert_eq!("%CA%B3%EA%AA", encode_keywords_with("食戟", GBK).unwrap());
+    assert_eq!("%B6i%C0%BB", encode_keywords_with("進擊", BIG5).unwrap());
+}
+
 #[test]
 fn test_find_platforms() {
     let platforms = find_platforms(vec![Tag::Chinese], vec![]);
@@ -869,6 +1302,16 @@ pub fn domain_route(url: &str) -> Option<DomainRoute> {
     None
 }
 
+/// 画廊类网站（只有章节）的 `comic_re`。
+const NONE_RE: &str = "^-NONE-$";
+
+/// 来源是否为画廊类网站，即只有章节而没有漫画页面。
+pub fn is_gallery(domain: &str) -> bool {
+    ROUTES
+        .iter()
+        .any(|(d, (comic_re, _))| d == domain && comic_re.as_str() == NONE_RE)
+}
+
 def_routes![
     {
         :domain     => "www.bidongmh.com",
@@ -960,6 +1403,11 @@ def_routes![
         :comic_re   => r#"^https?://manganelo\.com/manga/.+"#,
         :chapter_re => r#"^https?://manganelo\.com/chapter/[^/]+/chapter_.+"#
     },
+    {
+        :domain     => "www.mangareader.net",
+        :comic_re   => r#"^https?://www\.mangareader\.net/[^/]+"#,
+        :chapter_re => r#"^https?://www\.mangareader\.net/[^/]+/\d+"#
+    },
     {
         :domain     => "www.manhuadb.com",
         :comic_re   => r#"^https?://www\.manhuadb\.com/manhua/.+"#,
@@ -24,7 +24,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.bidongmh.com/search?keyword={}", keywords);
+        let url = format!("https://www.bidongmh.com/search?keyword={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -27,9 +27,9 @@ def_extractor! {
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
         let url = &if page == 1 {
-            format!("https://www.bnmanhua.com/index.php?m=vod-search-wd-{}.html", keywords)
+            format!("https://www.bnmanhua.com/index.php?m=vod-search-wd-{}.html", encode_keywords(keywords))
         } else {
-            format!("https://www.bnmanhua.com/index.php?m=vod-search-pg-{}-wd-{}.html", page, keywords)
+            format!("https://www.bnmanhua.com/index.php?m=vod-search-pg-{}-wd-{}.html", page, encode_keywords(keywords))
         };
 
         itemsgen2!(
@@ -92,7 +92,7 @@ def_extractor! {
             Ok(vec![Page::new(current_page - 1, address)])
         });
 
-        Ok(ChapterPages::new(chapter, len, vec![], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(len as usize), vec![], fetch))
     }
 }
 
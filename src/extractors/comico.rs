@@ -66,8 +66,8 @@ def_extractor! {
         Ok(comics)
     }
 
-    fn search(&self, keyworkds: &str) -> Result<Vec<Comic>> {
-        let url = format!("http://www.comico.com.tw/search/index.nhn?searchWord={}", keyworkds);
+    fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
+        let url = format!("http://www.comico.com.tw/search/index.nhn?searchWord={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
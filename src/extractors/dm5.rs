@@ -9,7 +9,7 @@ def_regex2![
 
 def_extractor! {
     status	=> [
-        usable: true, pageable: false, searchable: true, https: true, pageable_search: true,
+        usable: true, pageable: false, searchable: true, https: true, pageable_search: true, expiring: true,
         favicon: "https://www.dm5.com/favicon.ico"
     ],
     tags	=> [Chinese],
@@ -36,7 +36,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = format!("https://www.dm5.com/search?title={}&page={}", keywords, page);
+        let url = format!("https://www.dm5.com/search?title={}&page={}", encode_keywords(keywords), page);
         let html = get(&url)?.text()?;
         let document = parse_document(&html);
         let mut comics = vec![];
@@ -156,9 +156,17 @@ def_extractor! {
                 Ok(vec![Page::new(current_page - 1, address)])
             });
 
-            Ok(ChapterPages::new(chapter, page_count, first_page_addresses, fetch))
+            Ok(ChapterPages::new(chapter, Total::Known(page_count as usize), first_page_addresses, fetch))
         }
     }
+
+    // 图片防盗链检查 Referer 是否来自本站
+    fn page_request(&self, client: &Client, _chapter: &Chapter, page: &Page) -> Result<RequestBuilder> {
+        Ok(client
+            .get(&page.address)
+            .header(USER_AGENT, DEFAULT_USER_AGENT)
+            .header(REFERER, "https://www.dm5.com/"))
+    }
 }
 
 #[test]
@@ -51,7 +51,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://sacg.dmzj.com/comicsum/search.php?s={}", keywords);
+        let url = format!("https://sacg.dmzj.com/comicsum/search.php?s={}", encode_keywords(keywords));
         let html = get(&url)?.text()?;
         let mut comics = vec![];
         if let Ok(search_data) = match_content2!(&html, &*DATA_RE) {
@@ -26,7 +26,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = format!("https://e-hentai.org/?page={}&f_search={}", page - 1, keywords);
+        let url = format!("https://e-hentai.org/?page={}&f_search={}", page - 1, encode_keywords(keywords));
 
         itemsgen2!(
             url                 = &url,
@@ -71,7 +71,12 @@ def_extractor! {
             Ok(vec![Page::new(current_page - 1, address)])
         });
 
-        Ok(ChapterPages::new(chapter, total as i32, vec![], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(total as usize), vec![], fetch))
+    }
+
+    // 超出浏览限额时图片地址会被替换为 509.gif
+    fn placeholders(&self) -> Vec<Signature> {
+        vec![Signature::AddressSuffix(String::from("/509.gif"))]
     }
 }
 
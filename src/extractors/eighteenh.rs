@@ -47,7 +47,7 @@ def_extractor! {
             Ok(vec![Page::new(current_page - 1, address)])
         });
 
-        Ok(ChapterPages::new(chapter, total, vec![], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(total as usize), vec![], fetch))
     }
 }
 
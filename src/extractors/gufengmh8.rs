@@ -24,8 +24,8 @@ def_extractor! {
         )
     }
 
-    fn search(&self, keyworkds: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.gufengmh8.com/search/?keywords={}", keyworkds);
+    fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
+        let url = format!("https://www.gufengmh8.com/search/?keywords={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
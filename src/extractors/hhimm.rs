@@ -25,7 +25,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("http://www.hhimm.com/comic/?act=search&st={}", keywords);
+        let url = format!("http://www.hhimm.com/comic/?act=search&st={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -85,7 +85,7 @@ def_extractor! {
             Ok(vec![Page::new(current_page - 1, address)])
         });
 
-        Ok(ChapterPages::new(chapter, page_count as i32, vec![], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(page_count as usize), vec![], fetch))
     }
 }
 
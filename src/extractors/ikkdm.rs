@@ -1,5 +1,4 @@
 use super::*;
-use url::form_urlencoded::byte_serialize;
 
 def_regex2![
     INDEX_NAME  => r#"(.+)\[\d+\]?$"#,
@@ -42,9 +41,11 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let keywords_bytes = &encode_text(keywords, GBK)?[..];
-        let keywords_encoded: String = byte_serialize(keywords_bytes).collect();
-        let url = format!("http://so.kukudm.com/search.asp?kw={}&page={}", keywords_encoded, page);
+        let url = format!(
+            "http://so.kukudm.com/search.asp?kw={}&page={}",
+            encode_keywords_with(keywords, GBK)?,
+            page
+        );
 
         let mut comics = itemsgen2!(
             url             = &url,
@@ -99,7 +100,7 @@ def_extractor! {
             Ok(vec![Page::new(current_page, address)])
         });
 
-        Ok(ChapterPages::new(chapter, page_counut as i32, vec![first_address], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(page_counut as usize), vec![first_address], fetch))
     }
 }
 
@@ -61,8 +61,11 @@ def_extractor! {
         Ok(comics)
     }
 
-    fn search(&self, keyworkds: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.kuaikanmanhua.com/v1/search/topic?q={}&f=3&size=18", keyworkds);
+    fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
+        let url = format!(
+            "https://www.kuaikanmanhua.com/v1/search/topic?q={}&f=3&size=18",
+            encode_keywords(keywords)
+        );
 
         let json = get(&url)?.json::<SearchJson>()?;
         let mut comics = vec![];
@@ -61,7 +61,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://loveheaven.net/app/manga/controllers/search.single.php?q={}", keywords);
+        let url = format!("https://loveheaven.net/app/manga/controllers/search.single.php?q={}", encode_keywords(keywords));
 
         let comics = get(&url)?.json::<Vec<SearchJson>>()?
             .iter()
@@ -51,9 +51,10 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let mut url = String::from(r#"https://api.luscious.net/graphql/nobatch/?operationName=AlbumList&query=+query+AlbumList($input:+AlbumListInput!)+{+album+{+list(input:+$input)+{+info+{+...FacetCollectionInfo+}+items+{+...AlbumMinimal+}+}+}+}+fragment+FacetCollectionInfo+on+FacetCollectionInfo+{+page+has_next_page+has_previous_page+total_items+total_pages+items_per_page+url_complete+}+fragment+AlbumMinimal+on+Album+{+__typename+id+title+labels+description+created+modified+like_status+moderation_status+number_of_favorites+number_of_dislikes+number_of_pictures+number_of_animated_pictures+number_of_duplicates+slug+is_manga+url+download_url+permissions+created_by+{+id+url+name+display_name+user_title+avatar+{+url+size+}+}+cover+{+width+height+size+url+}+content+{+id+title+url+}+language+{+id+title+url+}+tags+{+category+text+url+count+}+genres+{+id+title+slug+url+}+audiences+{+id+title+url+}+}+&variables={"input":{"display":"search_score","filters":[{"name":"album_type","value":"manga"},{"name":"audience_ids","value":"+1+10+2+3+5+6+8+9"},{"name":"language_ids","value":"+1+100+101+2+3+4+5+6+8+9+99"},{"name":"search_query","value":""#);
-        url.push_str(keywords);
-        url.push_str(r#""}],"page":"#);
+        let mut url = String::from(r#"https://api.luscious.net/graphql/nobatch/?operationName=AlbumList&query=+query+AlbumList($input:+AlbumListInput!)+{+album+{+list(input:+$input)+{+info+{+...FacetCollectionInfo+}+items+{+...AlbumMinimal+}+}+}+}+fragment+FacetCollectionInfo+on+FacetCollectionInfo+{+page+has_next_page+has_previous_page+total_items+total_pages+items_per_page+url_complete+}+fragment+AlbumMinimal+on+Album+{+__typename+id+title+labels+description+created+modified+like_status+moderation_status+number_of_favorites+number_of_dislikes+number_of_pictures+number_of_animated_pictures+number_of_duplicates+slug+is_manga+url+download_url+permissions+created_by+{+id+url+name+display_name+user_title+avatar+{+url+size+}+}+cover+{+width+height+size+url+}+content+{+id+title+url+}+language+{+id+title+url+}+tags+{+category+text+url+count+}+genres+{+id+title+slug+url+}+audiences+{+id+title+url+}+}+&variables={"input":{"display":"search_score","filters":[{"name":"album_type","value":"manga"},{"name":"audience_ids","value":"+1+10+2+3+5+6+8+9"},{"name":"language_ids","value":"+1+100+101+2+3+4+5+6+8+9+99"},{"name":"search_query","value":"#);
+        // 关键字需要先转义为 JSON 字符串，再转义为 URL 参数
+        url.push_str(&encode_keywords(&serde_json::to_string(keywords)?));
+        url.push_str(r#"}],"page":"#);
         url.push_str(&page.to_string());
         url.push_str(r#"}}"#);
         let json_v = get(&url)?.json::<Value>()?;
@@ -10,7 +10,7 @@ def_regex2![
 // - 支持简繁体切换
 def_extractor! {
 	status	=> [
-		usable: true, pageable: true, searchable: true, https: false,
+		usable: true, pageable: true, searchable: true, https: false, expiring: true,
 		favicon: "http://www.mangabz.com/favicon.ico"
 	],
 	tags	=> [Chinese],
@@ -28,7 +28,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("http://www.mangabz.com/search?title={}", keywords);
+        let url = format!("http://www.mangabz.com/search?title={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -93,7 +93,15 @@ def_extractor! {
             Ok(pages)
         });
 
-        Ok(ChapterPages::new(chapter, *total, vec![], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(*total as usize), vec![], fetch))
+    }
+
+    // 同 dm5，图片防盗链要求本站的 Referer
+    fn page_request(&self, client: &Client, _chapter: &Chapter, page: &Page) -> Result<RequestBuilder> {
+        Ok(client
+            .get(&page.address)
+            .header(USER_AGENT, DEFAULT_USER_AGENT)
+            .header(REFERER, "http://www.mangabz.com/"))
     }
 }
 
@@ -20,7 +20,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://manganelo.com/search/{}", keywords);
+        let url = format!("https://manganelo.com/search/{}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
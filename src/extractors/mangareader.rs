@@ -35,7 +35,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = &format!("http://www.mangareader.net/search/?w={}&p={}", keywords, (page - 1) * 30);
+        let url = &format!("http://www.mangareader.net/search/?w={}&p={}", encode_keywords(keywords), (page - 1) * 30);
 
         let mut comics = itemsgen2!(
             url             = url,
@@ -88,7 +88,7 @@ def_extractor! {
             Ok(vec![Page::new(current_page, address)])
         });
 
-        Ok(ChapterPages::new(chapter, total as i32, vec![first_address], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(total as usize), vec![first_address], fetch))
     }
 }
 
@@ -28,7 +28,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.manhuadb.com/search?q={}", keywords);
+        let url = format!("https://www.manhuadb.com/search?q={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -24,7 +24,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.manhuadui.com/search/?keywords={}", keywords);
+        let url = format!("https://www.manhuadui.com/search/?keywords={}", encode_keywords(keywords));
 
         itemsgen2!(
             url         = &url,
@@ -1,4 +1,6 @@
 use super::*;
+use crate::processing::{ConvertFormat, Format};
+use reqwest::header::REFERER;
 
 def_regex2![
     CTYPTO => r#"window\["\\x65\\x76\\x61\\x6c"\]\((.+)\)\s+</script>"#
@@ -6,7 +8,7 @@ def_regex2![
 
 def_extractor! {
     status	=> [
-        usable: true, pageable: false, searchable: true, https: true,
+        usable: true, pageable: false, searchable: true, https: true, expiring: true,
         favicon: "https://www.manhuagui.com/favicon.ico"
     ],
     tags	=> [Chinese],
@@ -25,7 +27,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.manhuagui.com/s/{}.html", keywords);
+        let url = format!("https://www.manhuagui.com/s/{}.html", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -40,6 +42,8 @@ def_extractor! {
     fn fetch_chapters(&self, comic: &mut Comic) -> Result<()> {
         let html = &get(&comic.url)?.text()?;
         let document = parse_document(&html);
+        // 分组名称（单话、单行本等）位于章节列表之前
+        let group_names = document.dom_texts("h4 > span")?;
         for (i, elem) in document.select(&parse_selector(r#"div[id^="chapter-list-"]"#)?).enumerate() {
             let selector =  GroupedItemsSelector {
                 document: Rc::new(parse_document(&elem.html())),
@@ -49,7 +53,8 @@ def_extractor! {
                 items_url_prefix: "https://www.manhuagui.com",
                 ..Default::default()
             };
-            comic.chapters.append(&mut selector.gen()?.reversed_flatten(i));
+            let group_name = group_names.get(i).cloned().unwrap_or(format!("第{}组", i + 1));
+            comic.chapters.append(&mut selector.gen()?.reversed_flatten(i).set_group(&group_name));
         }
 
         Ok(())
@@ -94,7 +99,20 @@ def_extractor! {
             Ok(vec![Page::new(current_page - 1, address)])
         });
 
-        Ok(ChapterPages::new(chapter, total, vec![], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(total as usize), vec![], fetch))
+    }
+
+    // 图片服务器只接受本站的 Referer，不依赖章节的请求头（反序列化的章节可能没有）
+    fn page_request(&self, client: &Client, _chapter: &Chapter, page: &Page) -> Result<RequestBuilder> {
+        Ok(client
+            .get(&page.address)
+            .header(USER_AGENT, DEFAULT_USER_AGENT)
+            .header(REFERER, "https://www.manhuagui.com/"))
+    }
+
+    // 图片多为 WebP（`.jpg.webp`），转换为 JPEG 以便阅读器和导出格式兼容
+    fn processors(&self) -> Vec<ProcessorObject> {
+        vec![Box::new(ConvertFormat::webp(Format::Jpeg(90)))]
     }
 }
 
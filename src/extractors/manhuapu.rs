@@ -27,7 +27,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.manhuapu.com/statics/search.aspx?key={}", keywords);
+        let url = format!("https://www.manhuapu.com/statics/search.aspx?key={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
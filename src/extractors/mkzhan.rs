@@ -22,7 +22,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = &format!("https://www.mkzhan.com/search/?keyword={}&page={}", keywords, page);
+        let url = &format!("https://www.mkzhan.com/search/?keyword={}&page={}", encode_keywords(keywords), page);
 
         itemsgen2!(
             url             = url,
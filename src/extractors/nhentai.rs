@@ -33,7 +33,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = format!("https://nhentai.net/search/?q={}&page={}", keywords, page);
+        let url = format!("https://nhentai.net/search/?q={}&page={}", encode_keywords(keywords), page);
 
         let mut comics = itemsgen2!(
             url             = &url,
@@ -70,9 +70,9 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let mut body = String::from(r#"{"search":{"text":""#);
-        body.push_str(&keywords);
-        body.push_str(r#"","page":0,"sort":0,"pages":{"range":[0,2000]},"tag":{"text":"","type":1,"tags":[],"items":{"included":[],"excluded":[]}}}}"#);
+        let mut body = String::from(r#"{"search":{"text":"#);
+        body.push_str(&serde_json::to_string(keywords)?);
+        body.push_str(r#","page":0,"sort":0,"pages":{"range":[0,2000]},"tag":{"text":"","type":1,"tags":[],"items":{"included":[],"excluded":[]}}}}"#);
         let client = Client::new();
         let json = client
             .post("https://9hentai.com/api/getBook")
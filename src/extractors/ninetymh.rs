@@ -25,7 +25,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = &format!("http://www.90mh.com/search/?keywords={}&page={}", keywords, page);
+        let url = &format!("http://www.90mh.com/search/?keywords={}&page={}", encode_keywords(keywords), page);
 
         itemsgen2!(
             url             = url,
@@ -49,7 +49,7 @@ def_extractor! {
                 items_url_prefix: "http://www.90mh.com",
                 ..Default::default()
             };
-            comic.chapters.append(&mut selector.gen()?.flatten(i));
+            comic.chapters.append(&mut selector.gen()?.flatten(i).set_group(&format!("第{}组", i + 1)));
         }
 
         Ok(())
@@ -26,7 +26,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = format!("http://www.177pic.info/page/{}/?s={}", page, keywords);
+        let url = format!("http://www.177pic.info/page/{}/?s={}", page, encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -83,7 +83,7 @@ def_extractor! {
             Ok(pages)
         });
 
-        Ok(ChapterPages::new(chapter, total, first_addresses, fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(total as usize), first_addresses, fetch))
     }
 }
 
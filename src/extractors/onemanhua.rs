@@ -27,7 +27,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.ohmanhua.com/search?searchString={}", keywords);
+        let url = format!("https://www.ohmanhua.com/search?searchString={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -33,7 +33,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.qimiaomh.com/action/Search?keyword={}", keywords);
+        let url = format!("https://www.qimiaomh.com/action/Search?keyword={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -44,8 +44,8 @@ def_extractor! {
         Ok(comics)
     }
 
-    fn search(&self, keyworkds: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.tohomh123.com/action/Search?keyword={}", keyworkds);
+    fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
+        let url = format!("https://www.tohomh123.com/action/Search?keyword={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -90,7 +90,7 @@ def_extractor! {
             Ok(vec![Page::new(current_page - 1, json.code)])
         });
 
-        Ok(ChapterPages::new(chapter, total, vec![], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(total as usize), vec![], fetch))
     }
 }
 
@@ -62,7 +62,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = &format!("https://www.tvbsmh.com/search?searhword={}&page={}", keywords, page);
+        let url = &format!("https://www.tvbsmh.com/search?searhword={}&page={}", encode_keywords(keywords), page);
 
         itemsgen2!(
             url             = url,
@@ -160,7 +160,7 @@ def_extractor! {
                 .collect::<Vec<_>>())
         });
 
-        Ok(ChapterPages::new(chapter, total as i32, first_addresses, fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(total as usize), first_addresses, fetch))
     }
 }
 
@@ -28,7 +28,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = format!("http://twhentai.com/search/{}/{}/", keywords, page);
+        let url = format!("http://twhentai.com/search/{}/{}/", encode_keywords(keywords), page);
 
         itemsgen2!(
             url             = &url,
@@ -71,16 +71,18 @@ def_extractor! {
         } else {
             1
         };
+        // 最后一页的图片数量未知，直到最后一页解析完为止
         let total = if last_page_num > 1 {
-            let last_page_html = get(&format!("{}_p{}/", pure_url, last_page_num))?.text()?;
-            let last_page_document = parse_document(&last_page_html);
-            get_page_addresses(&last_page_document)?.len() + (last_page_num - 1) * 16
+            Total::Unknown
         } else {
-            first_addresses.len()
+            Total::Known(first_addresses.len())
         };
 
         let fetch = Box::new(move |current_page: usize| {
             let page_num = (current_page as f64 / 16.0f64).ceil() as usize;
+            if page_num > last_page_num {
+                return Ok(vec![]);
+            }
             let page_url = format!("{}_p{}/", pure_url, page_num);
             let page_html = get(&page_url)?.text()?;
             let page_document = parse_document(&page_html);
@@ -88,12 +90,14 @@ def_extractor! {
                 .iter()
                 .enumerate()
                 .map(|(i, addr)| Page::new((page_num - 1) * 16 + i, addr))
+                // 最后一页不足 16 张时，后续页码仍落在最后一页，只保留尚未解析的页面
+                .filter(|page| page.n + 1 >= current_page)
                 .collect::<Vec<_>>();
 
             Ok(pages)
         });
 
-        Ok(ChapterPages::new(chapter, total as i32, first_addresses, fetch))
+        Ok(ChapterPages::new(chapter, total, first_addresses, fetch))
     }
 }
 
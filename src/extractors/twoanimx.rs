@@ -23,7 +23,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://www.2animx.com/search-index?searchType=1&q={}", keywords);
+        let url = format!("https://www.2animx.com/search-index?searchType=1&q={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
@@ -69,7 +69,7 @@ def_extractor! {
             Ok(pages)
         });
 
-        Ok(ChapterPages::new(chapter, total, vec![], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(total as usize), vec![], fetch))
     }
 }
 
@@ -35,7 +35,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = format!("https://www.wnacg.org/albums-index-page-{}-sname-{}.html", page, keywords);
+        let url = format!("https://www.wnacg.org/albums-index-page-{}-sname-{}.html", page, encode_keywords(keywords));
 
         let mut comics = itemsgen2!(
             url             = &url,
@@ -122,7 +122,7 @@ def_extractor! {
             Ok(pages)
         });
 
-        Ok(ChapterPages::new(chapter, total, vec![], fetch))
+        Ok(ChapterPages::new(chapter, Total::Known(total as usize), vec![], fetch))
     }
 }
 
@@ -26,7 +26,7 @@ def_extractor! {
     }
 
     fn paginated_search(&self, keywords: &str, page: u32) -> Result<Vec<Comic>> {
-        let url = format!("http://www.wuqimh.com/search/q_{}-p-{}", keywords, page);
+        let url = format!("http://www.wuqimh.com/search/q_{}-p-{}", encode_keywords(keywords), page);
 
         itemsgen2!(
             url             = &url,
@@ -51,7 +51,9 @@ def_extractor! {
                 items_url_prefix: "http://www.wuqimh.com",
                 ..Default::default()
             };
-            comic.chapters.append(&mut selector.gen::<Chapter>()?.reversed_flatten(i));
+            // 每个分组由多个列表组成，按分组而不是列表命名
+            let chapters = selector.gen::<Chapter>()?.reversed_flatten(i);
+            comic.chapters.append(&mut chapters.set_group(&format!("第{}组", i + 1)));
         }
 
         Ok(())
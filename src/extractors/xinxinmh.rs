@@ -28,7 +28,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://so.177mh.net/k.php?k={}", keywords);
+        let url = format!("https://so.177mh.net/k.php?k={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
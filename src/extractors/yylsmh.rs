@@ -31,7 +31,7 @@ def_extractor! {
     }
 
     fn search(&self, keywords: &str) -> Result<Vec<Comic>> {
-        let url = format!("https://8comic.se/搜尋結果/?w={}", keywords);
+        let url = format!("https://8comic.se/搜尋結果/?w={}", encode_keywords(keywords));
 
         itemsgen2!(
             url             = &url,
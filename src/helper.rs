@@ -12,3 +12,6 @@ pub fn parse_document(html: &str) -> Html {
 
 pub mod document_ext;
 pub mod grouped_items;
+pub mod mime;
+pub mod normalize;
+pub mod numbering;
@@ -1,5 +1,5 @@
 use super::*;
-use crate::models::{FromLink, SetWhich};
+use crate::models::{FromLink, SetGroup, SetWhich};
 use scraper::Html;
 use std::default::Default;
 pub use std::rc::Rc;
@@ -134,13 +134,14 @@ pub trait Flatten<T> {
 
 const GROUP_SPACING: usize = 10000;
 
-impl<T: FromLink + SetWhich + Clone> Flatten<T> for Vec<GroupedItemsType<T>> {
+impl<T: FromLink + SetWhich + SetGroup + Clone> Flatten<T> for Vec<GroupedItemsType<T>> {
     fn flatten(&mut self, group_index: usize) -> Vec<T> {
         let mut items = vec![];
         let mut current_count = 0;
         for group in self.iter_mut() {
             for (i, item) in group.1.iter_mut().enumerate() {
                 item.set_which(GROUP_SPACING * group_index + current_count + i + 1);
+                item.set_group(&group.0);
                 items.push(item.clone());
             }
             current_count += group.1.len();
@@ -156,6 +157,7 @@ impl<T: FromLink + SetWhich + Clone> Flatten<T> for Vec<GroupedItemsType<T>> {
             group.1.reverse();
             for (i, item) in group.1.iter_mut().enumerate() {
                 item.set_which(GROUP_SPACING * group_index + current_count + i + 1);
+                item.set_group(&group.0);
                 items.push(item.clone());
             }
             current_count += group.1.len();
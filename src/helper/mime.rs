@@ -0,0 +1,132 @@
+use url::Url;
+
+pub static DEFAULT_MIME: &'static str = "*/*";
+
+// 已知的图片类型（MIME 与扩展名），首个扩展名为首选扩展名
+static IMAGE_TYPES: &[(&str, &[&str])] = &[
+    ("image/jpeg", &["jpg", "jpeg", "jpe", "jfif"]),
+    ("image/png", &["png"]),
+    ("image/gif", &["gif"]),
+    ("image/webp", &["webp"]),
+    ("image/bmp", &["bmp"]),
+    ("image/avif", &["avif"]),
+    ("image/tiff", &["tif", "tiff"]),
+    ("image/svg+xml", &["svg"]),
+];
+
+pub fn from_extension(extension: &str) -> Option<&'static str> {
+    let extension = extension.to_lowercase();
+    IMAGE_TYPES
+        .iter()
+        .find(|(_, extensions)| extensions.contains(&extension.as_str()))
+        .map(|(mime, _)| *mime)
+}
+
+pub fn to_extension(mime: &str) -> Option<&'static str> {
+    let mime = essence(mime);
+    IMAGE_TYPES
+        .iter()
+        .find(|(m, _)| *m == mime)
+        .map(|(_, extensions)| extensions[0])
+}
+
+// 去掉参数部分（例如 `image/jpeg; charset=binary`），并统一为小写
+fn essence(mime: &str) -> String {
+    let mime = mime.split(';').next().unwrap_or("").trim().to_lowercase();
+    match mime.as_str() {
+        "image/jpg" | "image/pjpeg" => String::from("image/jpeg"),
+        "image/x-png" => String::from("image/png"),
+        "image/x-ms-bmp" => String::from("image/bmp"),
+        _ => mime,
+    }
+}
+
+/// 从响应的 Content-Type 中识别图片类型，非图片类型（例如 `application/octet-stream`）返回 `None`。
+pub fn from_content_type(content_type: &str) -> Option<&'static str> {
+    let mime = essence(content_type);
+    IMAGE_TYPES
+        .iter()
+        .find(|(m, _)| *m == mime)
+        .map(|(mime, _)| *mime)
+}
+
+/// 根据文件头的魔数识别图片类型。
+pub fn sniff(data: &[u8]) -> Option<&'static str> {
+    let starts = |magic: &[u8]| data.len() >= magic.len() && &data[..magic.len()] == magic;
+
+    if starts(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if starts(b"\x89PNG\r\n\x1A\n") {
+        Some("image/png")
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        Some("image/gif")
+    } else if starts(b"RIFF") && data.len() >= 12 && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if starts(b"BM") && data.len() >= 14 {
+        Some("image/bmp")
+    } else if data.len() >= 12 && &data[4..8] == b"ftyp" && (&data[8..12] == b"avif" || &data[8..12] == b"avis") {
+        Some("image/avif")
+    } else if starts(b"II*\x00") || starts(b"MM\x00*") {
+        Some("image/tiff")
+    } else {
+        None
+    }
+}
+
+/// 从地址中识别图片扩展名。
+///
+/// 优先使用路径最后一段的扩展名，其次查找查询参数中以图片扩展名结尾的值（例如 `show.php?file=001.jpg`），
+/// 同时支持 `data:` 地址。
+pub fn extension_from_address(address: &str) -> Option<String> {
+    if address.starts_with("data:") {
+        let mime = address[5..].split(|c| c == ';' || c == ',').next().unwrap_or("");
+        return to_extension(mime).map(|ext| ext.to_string());
+    }
+    let image_extension = |s: &str| -> Option<String> {
+        let ext = s.rsplit('.').next()?;
+        if ext.len() < s.len() && from_extension(ext).is_some() {
+            Some(ext.to_lowercase())
+        } else {
+            None
+        }
+    };
+    if let Ok(url) = Url::parse(address) {
+        let from_path = url
+            .path_segments()
+            .and_then(|segments| segments.last())
+            .and_then(image_extension);
+        if from_path.is_some() {
+            return from_path;
+        }
+        url.query_pairs()
+            .filter_map(|(_, value)| image_extension(&value))
+            .next()
+    } else {
+        // 非完整 URL，去掉查询参数和片段后直接检查
+        let path = address.split(|c| c == '?' || c == '#').next().unwrap_or("");
+        image_extension(path.rsplit('/').next().unwrap_or(""))
+    }
+}
+
+pub fn from_address(address: &str) -> Option<&'static str> {
+    extension_from_address(address).and_then(|ext| from_extension(&ext))
+}
+
+#[test]
+fn test_from_address() {
+    assert_eq!(Some("image/webp"), from_address("https://i.hamreus.com/ps3/s/001.jpg.webp?e=1&m=2"));
+    assert_eq!(Some("image/png"), from_address("https://example.com/show.php?file=001.PNG"));
+    assert_eq!(None, from_address("https://cdn.example.com/image/8f3a2c"));
+    assert_eq!(None, from_address("https://example.com/show.php?id=1"));
+    assert_eq!(Some("image/gif"), from_address("data:image/gif;base64,R0lGODlhAQABAAAAACw="));
+}
+
+#[test]
+fn test_sniff() {
+    assert_eq!(Some("image/jpeg"), sniff(&[0xFF, 0xD8, 0xFF, 0xE0]));
+    assert_eq!(Some("image/png"), sniff(b"\x89PNG\r\n\x1A\n\x00\x00"));
+    assert_eq!(Some("image/webp"), sniff(b"RIFF\x00\x00\x00\x00WEBPVP8 "));
+    assert_eq!(None, sniff(b"<html>"));
+    assert_eq!(Some("image/jpeg"), from_content_type("image/jpg; charset=binary"));
+    assert_eq!(None, from_content_type("application/octet-stream"));
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+// 常用繁体字及对应的简体字（按位置一一对应），用于比较标题时统一字形
+static TRADITIONAL: &str = concat!(
+    "萬與醜專業叢東絲兩嚴喪個豐臨為麗舉義烏樂喬習鄉書買亂爭於虧雲亞產親億僅從侖倉儀們",
+    "價眾優會傘偉傳傷倫偽體餘傭僉俠侶僥偵側僑儈儕儂儉債傾僂僨償儲兒兌黨蘭關興養獸內岡",
+    "冊寫軍農馮衝決況凍淨準涼減湊凜幾鳳憑凱擊鑿劃劉則剛創刪別剎劑剮劍劇勸辦務動勵勁勞",
+    "勢勳勻匯區醫華協單賣盧衛卻廠廳歷厲壓厭廁廂縣參雙發變敘疊葉號嘆嘰後嚇呂嗎噸聽啟吳",
+    "嘔員嗚詠哢響啞噴嘩喲嚨園圍國圖圓聖場壞塊堅壇壩墳墜壟壘墾執堊墊塹牆壯聲殼壺處備復",
+    "夠頭誇夾奪奮獎奧妝婦媽嫵嬌孫學孿寧寶實寵審憲宮寬賓對尋導將爾塵嘗堯屍盡層屬歲豈島",
+    "嶺嶽峽幣師帳帶幀幫幹並廣莊慶廬庫應廟廢開異棄張彌彎彈強歸當錄彥徹徑憶懺憂懷態總戀",
+    "惡懸驚懼慘慣愛擔據擁擇掛擋揮損換搖攜撲擴掃擾搶護報撥擬攏擰擠擲攤敗斃斂數齋斷無舊",
+    "時曠晝顯晉曬曉暈暫術機殺雜權條來楊極構槍楓標棧欄樹樣橋夢檢歡歐殘殲殤毀氣漢湯溝沒",
+    "滄滅潑澤淚潔灑濁測濟瀏渾濃濤澀漁漸滲溫灣濕滿潛澆灘燈靈災爐點煉爛熱煩燒營燦爺牽犧",
+    "狀猶獨狹獄獵貓獻現環瑪瓊畫暢療瘋盜監盤睜矚礦碼磚礎禮禍離種積稱穩窮竊競筆築節範簡",
+    "簽籃類糧糾紀約紅紋純紙級紛組細終結給絕統經綠維網緊線練緣編縮績織繼續纏罰羅羨翹聞",
+    "聯職聰肅腦腳脫臉膽艦艱藝蘇蘋莖藥獲蕭薩藍蟲蝦蠻衆補裝襲見規視覺覽觀計訂認討讓訓記",
+    "講許論設訪證評識詞試詩話誠誤說請讀課誰調談謀謎謝謠謹譜豬貝負財貢貧貨販貪責貴費貼",
+    "貿賀資賊賞賢賭賴贈贊趕趙趨躍蹤車軌輪軟轉輕載較輔輛輝輩輸辭邊遼達遷過邁運還這進遠",
+    "違連遲適選遺邏鄰鄭醬釋裡鑒針釣鈴鐵鉛銀銅鋒鋼錢錯鍋鍵鏡鐘鑽長門閃閉問閒間閣闖闊闡",
+    "隊陽陰陣階際陸陳險隨隱隸難雞電霧靜韓頁頂項順須預領頻題額顏願顧風飛飯飲餓館饅馬駕",
+    "駛騎騙騰驗驅髮鬥鬧魚鮮鯨鳥鳴鴉鷹麥黃齊齒龍龜戰鏈詭蘿龐綜讎繪鍊觸墮嚮迴週剋鬆鬍麵",
+    "隻捲臺檯颱裏艷噹朮愾廈嶼轟鑰囉鎖獅縱賽魯頓滾漿劊櫃",
+);
+static SIMPLIFIED: &str = concat!(
+    "万与丑专业丛东丝两严丧个丰临为丽举义乌乐乔习乡书买乱争于亏云亚产亲亿仅从仑仓仪们",
+    "价众优会伞伟传伤伦伪体余佣佥侠侣侥侦侧侨侩侪侬俭债倾偻偾偿储儿兑党兰关兴养兽内冈",
+    "册写军农冯冲决况冻净准凉减凑凛几凤凭凯击凿划刘则刚创删别刹剂剐剑剧劝办务动励劲劳",
+    "势勋匀汇区医华协单卖卢卫却厂厅历厉压厌厕厢县参双发变叙叠叶号叹叽后吓吕吗吨听启吴",
+    "呕员呜咏咔响哑喷哗哟咙园围国图圆圣场坏块坚坛坝坟坠垄垒垦执垩垫堑墙壮声壳壶处备复",
+    "够头夸夹夺奋奖奥妆妇妈妩娇孙学孪宁宝实宠审宪宫宽宾对寻导将尔尘尝尧尸尽层属岁岂岛",
+    "岭岳峡币师帐带帧帮干并广庄庆庐库应庙废开异弃张弥弯弹强归当录彦彻径忆忏忧怀态总恋",
+    "恶悬惊惧惨惯爱担据拥择挂挡挥损换摇携扑扩扫扰抢护报拨拟拢拧挤掷摊败毙敛数斋断无旧",
+    "时旷昼显晋晒晓晕暂术机杀杂权条来杨极构枪枫标栈栏树样桥梦检欢欧残歼殇毁气汉汤沟没",
+    "沧灭泼泽泪洁洒浊测济浏浑浓涛涩渔渐渗温湾湿满潜浇滩灯灵灾炉点炼烂热烦烧营灿爷牵牺",
+    "状犹独狭狱猎猫献现环玛琼画畅疗疯盗监盘睁瞩矿码砖础礼祸离种积称稳穷窃竞笔筑节范简",
+    "签篮类粮纠纪约红纹纯纸级纷组细终结给绝统经绿维网紧线练缘编缩绩织继续缠罚罗羡翘闻",
+    "联职聪肃脑脚脱脸胆舰艰艺苏苹茎药获萧萨蓝虫虾蛮众补装袭见规视觉览观计订认讨让训记",
+    "讲许论设访证评识词试诗话诚误说请读课谁调谈谋谜谢谣谨谱猪贝负财贡贫货贩贪责贵费贴",
+    "贸贺资贼赏贤赌赖赠赞赶赵趋跃踪车轨轮软转轻载较辅辆辉辈输辞边辽达迁过迈运还这进远",
+    "违连迟适选遗逻邻郑酱释里鉴针钓铃铁铅银铜锋钢钱错锅键镜钟钻长门闪闭问闲间阁闯阔阐",
+    "队阳阴阵阶际陆陈险随隐隶难鸡电雾静韩页顶项顺须预领频题额颜愿顾风飞饭饮饿馆馒马驾",
+    "驶骑骗腾验驱发斗闹鱼鲜鲸鸟鸣鸦鹰麦黄齐齿龙龟战链诡萝庞综雠绘炼触堕向回周克松胡面",
+    "只卷台台台里艳当术忾厦屿轰钥啰锁狮纵赛鲁顿滚浆刽柜",
+);
+
+lazy_static! {
+    static ref TRADITIONAL_TO_SIMPLIFIED: HashMap<char, char> =
+        TRADITIONAL.chars().zip(SIMPLIFIED.chars()).collect();
+}
+
+/// 全角字符转为半角，全角空格转为普通空格。
+pub fn to_half_width(c: char) -> char {
+    match c {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => std::char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// 将常用繁体字转为简体字。
+pub fn to_simplified(c: char) -> char {
+    TRADITIONAL_TO_SIMPLIFIED.get(&c).copied().unwrap_or(c)
+}
+
+/// 统一字形和大小写，并去掉标点和空白，用于比较标题或作者。
+///
+/// 例如 `【食戟之靈】 ＳＨＯＫＵＧＥＫＩ！` 和 `食戟之灵 shokugeki` 的结果相同。
+pub fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .map(to_half_width)
+        .flat_map(|c| c.to_lowercase())
+        .map(to_simplified)
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF)
+}
+
+/// 将文本切分为用于检索的词（已统一字形和大小写，不重复）。
+///
+/// 拉丁字母和数字按单词切分，中日韩文字之间没有空格，切分为单字和相邻的二字组合，
+/// 例如 `食戟之靈 Shokugeki` 切分为 `食`、`食戟`、`戟`、`戟之`、`之`、`之灵`、`灵` 和 `shokugeki`。
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = vec![];
+    let mut push = |token: String| {
+        if !tokens.contains(&token) {
+            tokens.push(token);
+        }
+    };
+    let mut word = String::new();
+    let mut prev_cjk: Option<char> = None;
+    let chars = text
+        .chars()
+        .map(to_half_width)
+        .flat_map(|c| c.to_lowercase())
+        .map(to_simplified);
+    for c in chars {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                push(std::mem::take(&mut word));
+            }
+            if let Some(prev) = prev_cjk {
+                push(format!("{}{}", prev, c));
+            }
+            push(c.to_string());
+            prev_cjk = Some(c);
+        } else if c.is_alphanumeric() {
+            word.push(c);
+            prev_cjk = None;
+        } else {
+            if !word.is_empty() {
+                push(std::mem::take(&mut word));
+            }
+            prev_cjk = None;
+        }
+    }
+    if !word.is_empty() {
+        push(word);
+    }
+
+    tokens
+}
+
+/// 基于字符二元组的 Dice 系数（0-1），用于比较规范化后的标题。
+pub fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+    let bigrams = |text: &str| {
+        let chars = text.chars().collect::<Vec<_>>();
+        if chars.len() < 2 {
+            return chars.iter().map(|c| (*c, '\0')).collect::<Vec<_>>();
+        }
+        chars.windows(2).map(|w| (w[0], w[1])).collect::<Vec<_>>()
+    };
+    let (a, mut b) = (bigrams(a), bigrams(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let total = a.len() + b.len();
+    let mut common = 0;
+    for bigram in a {
+        if let Some(i) = b.iter().position(|other| *other == bigram) {
+            b.swap_remove(i);
+            common += 1;
+        }
+    }
+
+    2.0 * common as f64 / total as f64
+}
+
+#[test]
+fn test_normalize_title() {
+    assert_eq!("食戟之灵shokugeki", normalize_title("【食戟之靈】 ＳＨＯＫＵＧＥＫＩ！"));
+    assert_eq!(normalize_title("食戟之灵 shokugeki"), normalize_title("【食戟之靈】 ＳＨＯＫＵＧＥＫＩ！"));
+    assert_eq!("进击的巨人", normalize_title("進擊的巨人"));
+    assert_eq!(1.0, similarity("一拳超人", "一拳超人"));
+    assert!(similarity("食戟之灵", "食戟之灵完结") > 0.7);
+    assert!(similarity("食戟之灵", "一拳超人") < 0.1);
+    assert_eq!(0.0, similarity("", "一拳超人"));
+    assert_eq!(
+        vec!["食", "食戟", "戟", "戟之", "之", "之灵", "灵", "shokugeki"],
+        tokenize("食戟之靈 ＳＨＯＫＵＧＥＫＩ")
+    );
+    assert_eq!(vec!["one", "punch", "man", "一", "一拳", "拳"], tokenize("One-Punch Man 一拳 one"));
+}
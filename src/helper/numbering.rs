@@ -0,0 +1,125 @@
+use regex::Regex;
+
+lazy_static! {
+    static ref CHAPTER_NUMBER_RE: Regex =
+        Regex::new(r#"第\s*([0-9]+(?:\.[0-9]+)?|[零〇一二两三四五六七八九十百千]+)\s*[话話回章集节節篇]"#).unwrap();
+    static ref VOLUME_NUMBER_RE: Regex =
+        Regex::new(r#"第\s*([0-9]+(?:\.[0-9]+)?|[零〇一二两三四五六七八九十百千]+)\s*[卷部册冊]"#).unwrap();
+    static ref EN_CHAPTER_NUMBER_RE: Regex =
+        Regex::new(r#"(?i)(?:\b(?:chapter|chap|ch|episode|ep)\.?|#)\s*([0-9]+(?:\.[0-9]+)?)"#).unwrap();
+    static ref EN_VOLUME_NUMBER_RE: Regex =
+        Regex::new(r#"(?i)\b(?:volume|vol)\.?\s*([0-9]+(?:\.[0-9]+)?)"#).unwrap();
+    static ref ANY_NUMBER_RE: Regex = Regex::new(r#"([0-9]+(?:\.[0-9]+)?)"#).unwrap();
+}
+
+// 全角数字和小数点转为半角
+fn normalize_digits(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '０'..='９' => ((c as u32 - '０' as u32) as u8 + b'0') as char,
+            '．' => '.',
+            _ => c,
+        })
+        .collect()
+}
+
+/// 解析中文数字（例如 `一百二十三`、`十五`、`两千零一`）。
+pub fn parse_chinese_number(text: &str) -> Option<u32> {
+    let digit = |c: char| -> Option<u32> {
+        match c {
+            '零' | '〇' => Some(0),
+            '一' => Some(1),
+            '二' | '两' => Some(2),
+            '三' => Some(3),
+            '四' => Some(4),
+            '五' => Some(5),
+            '六' => Some(6),
+            '七' => Some(7),
+            '八' => Some(8),
+            '九' => Some(9),
+            _ => None,
+        }
+    };
+    let unit = |c: char| -> Option<u32> {
+        match c {
+            '十' => Some(10),
+            '百' => Some(100),
+            '千' => Some(1000),
+            _ => None,
+        }
+    };
+    if text.is_empty() {
+        return None;
+    }
+    // 不含单位时按位拼接（例如 `一〇二`）
+    if text.chars().all(|c| digit(c).is_some()) {
+        return text
+            .chars()
+            .try_fold(0u32, |acc, c| Some(acc * 10 + digit(c)?));
+    }
+    let mut total = 0;
+    let mut current: Option<u32> = None;
+    for c in text.chars() {
+        if let Some(d) = digit(c) {
+            current = Some(d);
+        } else if let Some(u) = unit(c) {
+            // `十五` 中省略了 `一`
+            total += current.unwrap_or(1) * u;
+            current = None;
+        } else {
+            return None;
+        }
+    }
+
+    Some(total + current.unwrap_or(0))
+}
+
+fn parse_captured(text: &str) -> Option<f64> {
+    text.parse::<f64>()
+        .ok()
+        .or_else(|| parse_chinese_number(text).map(|n| n as f64))
+}
+
+/// 从章节标题中解析话数，例如 `第12话`、`第十二話`、`Chapter 12.5`。
+///
+/// 没有明确标记时退回到标题中的第一个数字（卷数除外）。
+pub fn parse_chapter_number(title: &str) -> Option<f64> {
+    let title = &normalize_digits(title);
+    for re in &[&*CHAPTER_NUMBER_RE, &*EN_CHAPTER_NUMBER_RE] {
+        if let Some(caps) = re.captures(title) {
+            return parse_captured(&caps[1]);
+        }
+    }
+    if parse_volume_number(title).is_some() {
+        return None;
+    }
+    ANY_NUMBER_RE
+        .captures(title)
+        .and_then(|caps| caps[1].parse::<f64>().ok())
+}
+
+/// 从章节标题中解析卷数，例如 `第3卷`、`Vol.3`。
+pub fn parse_volume_number(title: &str) -> Option<f64> {
+    let title = &normalize_digits(title);
+    for re in &[&*VOLUME_NUMBER_RE, &*EN_VOLUME_NUMBER_RE] {
+        if let Some(caps) = re.captures(title) {
+            return parse_captured(&caps[1]);
+        }
+    }
+
+    None
+}
+
+#[test]
+fn test_parse_chapter_number() {
+    assert_eq!(Some(1.0), parse_chapter_number("神武天尊 第1回"));
+    assert_eq!(Some(123.0), parse_chapter_number("第一百二十三话 决战"));
+    assert_eq!(Some(15.0), parse_chapter_number("第十五話"));
+    assert_eq!(Some(12.5), parse_chapter_number("Vol.3 Chapter 12.5: The End"));
+    assert_eq!(Some(12.0), parse_chapter_number("第１２话"));
+    assert_eq!(Some(1.0), parse_chapter_number("三毛與捲毛第二季01 煩人警察"));
+    assert_eq!(None, parse_chapter_number("风云全集 第648卷 下"));
+    assert_eq!(None, parse_chapter_number("番外篇"));
+    assert_eq!(Some(648.0), parse_volume_number("风云全集 第648卷 下"));
+    assert_eq!(Some(3.0), parse_volume_number("Vol.3 Chapter 12.5"));
+}
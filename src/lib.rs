@@ -3,7 +3,23 @@ extern crate lazy_static;
 #[macro_use]
 extern crate failure;
 
+pub mod detection;
+pub mod download;
 pub mod error;
+pub mod export;
 pub mod extractors;
 pub mod helper;
+#[cfg(feature = "library")]
+pub mod library;
+pub mod matching;
 pub mod models;
+pub mod processing;
+pub mod progress;
+pub mod queue;
+pub mod ranking;
+pub mod resolve;
+pub mod search;
+pub mod sidecar;
+pub mod updates;
+
+pub use resolve::{resolve, Resolved};
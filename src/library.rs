@@ -0,0 +1,402 @@
+//! 基于 SQLite 的本地书架（订阅的漫画），需要开启 `library` 特性。
+//!
+//! 漫画以 `(来源域名, 漫画地址)` 作为标识，章节列表按顺序保存（不包含页面），阅读进度见 `reading`，
+//! 迁移到其它来源见 `migration`，导入和导出见 `backup`，检查更新见 `Library::check_updates`。
+use crate::extractors::get_extr;
+use crate::updates::{self, ChapterDiff};
+use crate::{error::*, models::*};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod backup;
+pub mod migration;
+pub mod reading;
+pub mod tachiyomi;
+
+/// 按顺序执行的数据库迁移，已执行的数量记录在 `user_version` 中。
+static MIGRATIONS: &[&str] = &[r#"
+CREATE TABLE comics (
+    id INTEGER PRIMARY KEY,
+    domain TEXT NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    cover TEXT NOT NULL,
+    author TEXT NOT NULL,
+    description TEXT NOT NULL,
+    tags TEXT NOT NULL,
+    last_updated_date INTEGER NOT NULL,
+    state INTEGER NOT NULL,
+    added_at INTEGER NOT NULL,
+    UNIQUE (domain, url)
+);
+CREATE TABLE chapters (
+    id INTEGER PRIMARY KEY,
+    comic_id INTEGER NOT NULL REFERENCES comics (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    which INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT NOT NULL,
+    UNIQUE (comic_id, url)
+);
+"#, r#"
+CREATE TABLE progress (
+    comic_id INTEGER NOT NULL REFERENCES comics (id) ON DELETE CASCADE,
+    chapter_key TEXT NOT NULL,
+    chapter_url TEXT NOT NULL,
+    state INTEGER NOT NULL,
+    page INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (comic_id, chapter_key)
+);
+CREATE TABLE history (
+    id INTEGER PRIMARY KEY,
+    comic_id INTEGER NOT NULL REFERENCES comics (id) ON DELETE CASCADE,
+    chapter_url TEXT NOT NULL,
+    chapter_title TEXT NOT NULL,
+    page INTEGER NOT NULL,
+    read_at INTEGER NOT NULL
+);
+"#];
+
+/// 书架中的漫画。
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub id: i64,
+    pub domain: String,
+    pub comic: Comic,
+    /// 加入书架的时间（Unix 时间戳，秒）
+    pub added_at: i64,
+}
+
+/// 查询条件，为 `None` 的条件不参与过滤。
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub domain: Option<String>,
+    /// 标题或作者包含的关键字
+    pub keywords: Option<String>,
+    pub tag: Option<String>,
+}
+
+/// 书架中一部漫画的更新检查结果。
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub domain: String,
+    pub url: String,
+    pub title: String,
+    pub result: std::result::Result<ChapterDiff, String>,
+}
+
+pub struct Library {
+    conn: Connection,
+}
+
+pub(crate) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+fn state_to_i64(state: &ComicState) -> i64 {
+    match state {
+        ComicState::Unknown => 0,
+        ComicState::Completed => 1,
+        ComicState::Ongoing => 2,
+    }
+}
+
+fn state_from_i64(value: i64) -> ComicState {
+    match value {
+        1 => ComicState::Completed,
+        2 => ComicState::Ongoing,
+        _ => ComicState::Unknown,
+    }
+}
+
+impl Library {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(mut conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        let version: i64 = conn.query_row("PRAGMA user_version", params![], |row| row.get(0))?;
+        let tx = conn.transaction()?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            tx.execute_batch(migration)?;
+            tx.execute_batch(&format!("PRAGMA user_version = {}", i + 1))?;
+        }
+        tx.commit()?;
+
+        Ok(Self { conn })
+    }
+
+    /// 添加漫画，已存在时更新元数据和章节列表（`comic.chapters` 为空时保留原有章节）。
+    pub fn add(&mut self, domain: &str, comic: &Comic) -> Result<i64> {
+        let tx = self.conn.transaction()?;
+        let id = upsert_comic(&tx, domain, comic)?;
+        tx.commit()?;
+
+        Ok(id)
+    }
+
+    /// 替换漫画的章节列表。
+    pub fn set_chapters(&mut self, domain: &str, url: &str, chapters: &[Chapter]) -> Result<()> {
+        let id = self
+            .id(domain, url)?
+            .ok_or_else(|| err_msg(format!("Comic not found in library: {}", url)))?;
+        let tx = self.conn.transaction()?;
+        replace_chapters(&tx, id, chapters)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// 检查书架中全部漫画的更新（见 `updates::check_updates`），并保存新的章节列表，单部漫画失败不会中断检查。
+    pub fn check_updates(&mut self) -> Result<Vec<Update>> {
+        let mut checked = vec![];
+        for entry in self.list()? {
+            let result = match get_extr(entry.domain.as_str()) {
+                Some(extr) => updates::check_updates(&**extr, &entry.comic),
+                None => Err(err_msg(format!("Unsupported domain: {}", entry.domain))),
+            };
+            let result = match result {
+                Ok((chapters, diff)) => {
+                    if !diff.is_empty() || chapters.len() != entry.comic.chapters.len() {
+                        self.set_chapters(&entry.domain, &entry.comic.url, &chapters)?;
+                    }
+                    Ok(diff)
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            checked.push(Update {
+                domain: entry.domain,
+                url: entry.comic.url,
+                title: entry.comic.title,
+                result,
+            });
+        }
+
+        Ok(checked)
+    }
+
+    /// 移除漫画，返回是否存在。
+    pub fn remove(&mut self, domain: &str, url: &str) -> Result<bool> {
+        let count = self.conn.execute(
+            "DELETE FROM comics WHERE domain = ?1 AND url = ?2",
+            params![domain, url],
+        )?;
+
+        Ok(count > 0)
+    }
+
+    pub fn contains(&self, domain: &str, url: &str) -> Result<bool> {
+        Ok(self.id(domain, url)?.is_some())
+    }
+
+    fn id(&self, domain: &str, url: &str) -> Result<Option<i64>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT id FROM comics WHERE domain = ?1 AND url = ?2",
+                params![domain, url],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub fn get(&self, domain: &str, url: &str) -> Result<Option<Entry>> {
+        let entry = self
+            .conn
+            .query_row(
+                &format!("{} WHERE domain = ?1 AND url = ?2", SELECT_COMICS),
+                params![domain, url],
+                read_entry,
+            )
+            .optional()?;
+        match entry {
+            Some(entry) => Ok(Some(self.with_chapters(entry)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 列出全部漫画，按加入时间排序。
+    pub fn list(&self) -> Result<Vec<Entry>> {
+        self.query(&Filter::default())
+    }
+
+    pub fn query(&self, filter: &Filter) -> Result<Vec<Entry>> {
+        let keywords = filter.keywords.as_ref().map(|k| format!("%{}%", escape_like(k)));
+        // 标签以 JSON 数组保存，匹配带引号的完整标签
+        let tag = filter
+            .tag
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?
+            .map(|t| format!("%{}%", escape_like(&t)));
+        let mut stmt = self.conn.prepare(&format!(
+            "{} WHERE (?1 IS NULL OR domain = ?1)
+                AND (?2 IS NULL OR title LIKE ?2 ESCAPE '\\' OR author LIKE ?2 ESCAPE '\\')
+                AND (?3 IS NULL OR tags LIKE ?3 ESCAPE '\\')
+             ORDER BY added_at, id",
+            SELECT_COMICS
+        ))?;
+        let entries = stmt
+            .query_map(params![filter.domain, keywords, tag], read_entry)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        entries.into_iter().map(|entry| self.with_chapters(entry)).collect()
+    }
+
+    fn with_chapters(&self, mut entry: Entry) -> Result<Entry> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT title, url, which FROM chapters WHERE comic_id = ?1 ORDER BY position")?;
+        entry.comic.chapters = stmt
+            .query_map(params![entry.id], |row| {
+                Ok(Chapter::new(
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, u32>(2)?,
+                ))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(entry)
+    }
+}
+
+static SELECT_COMICS: &str = "SELECT id, domain, url, title, cover, author, description, tags, last_updated_date, state, added_at FROM comics";
+
+fn read_entry(row: &Row) -> rusqlite::Result<Entry> {
+    let tags: String = row.get(7)?;
+    let comic = Comic {
+        url: row.get(2)?,
+        title: row.get(3)?,
+        cover: row.get(4)?,
+        author: row.get(5)?,
+        description: row.get(6)?,
+        tags: serde_json::from_str(&tags).unwrap_or_default(),
+        last_updated_date: row.get(8)?,
+        state: state_from_i64(row.get(9)?),
+        ..Default::default()
+    };
+
+    Ok(Entry {
+        id: row.get(0)?,
+        domain: row.get(1)?,
+        comic,
+        added_at: row.get(10)?,
+    })
+}
+
+fn upsert_comic(conn: &Connection, domain: &str, comic: &Comic) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO comics (domain, url, title, cover, author, description, tags, last_updated_date, state, added_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT (domain, url) DO UPDATE SET
+            title = excluded.title, cover = excluded.cover, author = excluded.author,
+            description = excluded.description, tags = excluded.tags,
+            last_updated_date = excluded.last_updated_date, state = excluded.state",
+        params![
+            domain,
+            comic.url,
+            comic.title,
+            comic.cover,
+            comic.author,
+            comic.description,
+            serde_json::to_string(&comic.tags)?,
+            comic.last_updated_date,
+            state_to_i64(&comic.state),
+            now(),
+        ],
+    )?;
+    let id: i64 = conn.query_row(
+        "SELECT id FROM comics WHERE domain = ?1 AND url = ?2",
+        params![domain, comic.url],
+        |row| row.get(0),
+    )?;
+    if !comic.chapters.is_empty() {
+        replace_chapters(conn, id, &comic.chapters)?;
+    }
+
+    Ok(id)
+}
+
+// 保留仍然存在的章节记录（按地址匹配），以免丢失关联数据
+fn replace_chapters(conn: &Connection, comic_id: i64, chapters: &[Chapter]) -> Result<()> {
+    let urls = serde_json::to_string(&chapters.iter().map(|c| &c.url).collect::<Vec<_>>())?;
+    conn.execute(
+        "DELETE FROM chapters WHERE comic_id = ?1 AND url NOT IN (SELECT value FROM json_each(?2))",
+        params![comic_id, urls],
+    )?;
+    for (position, chapter) in chapters.iter().enumerate() {
+        conn.execute(
+            "INSERT INTO chapters (comic_id, position, which, title, url) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (comic_id, url) DO UPDATE SET
+                position = excluded.position, which = excluded.which, title = excluded.title",
+            params![comic_id, position as i64, chapter.which, chapter.title, chapter.url],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+#[test]
+fn test_library() {
+    let mut library = Library::open_in_memory().unwrap();
+    let mut comic = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    comic.author = String::from("附田祐斗");
+    comic.tags = vec![String::from("美食")];
+    comic.state = ComicState::Completed;
+    comic.push_chapter(Chapter::new("第1话", "https://www.manhuagui.com/comic/2863/1.html", 1));
+    comic.push_chapter(Chapter::new("第2话", "https://www.manhuagui.com/comic/2863/2.html", 2));
+    let id = library.add("www.manhuagui.com", &comic).unwrap();
+    library
+        .add("www.manhuagui.com", &Comic::new("一拳超人", "https://www.manhuagui.com/comic/7580/"))
+        .unwrap();
+
+    // 重复添加时更新而不是新增
+    comic.title = String::from("食戟之灵（完结）");
+    assert_eq!(id, library.add("www.manhuagui.com", &Comic { chapters: vec![], ..comic.clone() }).unwrap());
+    let entry = library.get("www.manhuagui.com", &comic.url).unwrap().unwrap();
+    assert_eq!("食戟之灵（完结）", entry.comic.title);
+    assert_eq!(2, entry.comic.chapters.len());
+    assert_eq!(vec!["美食"], entry.comic.tags);
+
+    assert_eq!(2, library.list().unwrap().len());
+    let filter = Filter {
+        keywords: Some(String::from("附田")),
+        ..Default::default()
+    };
+    assert_eq!(1, library.query(&filter).unwrap().len());
+    let filter = Filter {
+        tag: Some(String::from("美食")),
+        domain: Some(String::from("www.manhuagui.com")),
+        ..Default::default()
+    };
+    assert_eq!(1, library.query(&filter).unwrap().len());
+    let filter = Filter {
+        domain: Some(String::from("www.dm5.com")),
+        ..Default::default()
+    };
+    assert!(library.query(&filter).unwrap().is_empty());
+
+    library
+        .set_chapters("www.manhuagui.com", &comic.url, &comic.chapters[1..])
+        .unwrap();
+    let entry = library.get("www.manhuagui.com", &comic.url).unwrap().unwrap();
+    assert_eq!("第2话", entry.comic.chapters[0].title);
+    assert!(library.remove("www.manhuagui.com", &comic.url).unwrap());
+    assert!(!library.contains("www.manhuagui.com", &comic.url).unwrap());
+}
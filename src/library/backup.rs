@@ -0,0 +1,198 @@
+//! 书架的导入和导出。
+//!
+//! 导出的 JSON 格式如下：
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "comics": [
+//!     {
+//!       "domain": "www.manhuagui.com",
+//!       "added_at": 1600000000,
+//!       "comic": { "title": "...", "url": "...", "chapters": [...], ... },
+//!       "progress": [{ "chapter_url": "...", "state": "in_progress", "page": 5, "updated_at": 1600000000 }]
+//!     }
+//!   ],
+//!   "history": [
+//!     {
+//!       "domain": "www.manhuagui.com", "comic_url": "...", "comic_title": "...",
+//!       "chapter_url": "...", "chapter_title": "...", "page": 5, "read_at": 1600000000
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! - `comic` 为 `Comic` 的 JSON 表示，章节不包含页面
+//! - `state` 为 `unread`、`in_progress`（带有从 1 开始的 `page`）或 `read`
+//! - 时间均为 Unix 时间戳（秒）
+//! - 导入时已存在的漫画会被更新，阅读进度只在比已有记录更新时覆盖，重复的历史记录会被忽略
+//!
+//! 另外支持导入 Tachiyomi/Mihon 的备份，见 `tachiyomi`。
+use super::reading::{upsert_progress, HistoryItem, Progress};
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// 当前的格式版本。
+pub const SCHEMA_VERSION: u64 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    pub schema_version: u64,
+    pub comics: Vec<BackupComic>,
+    #[serde(default)]
+    pub history: Vec<HistoryItem>,
+}
+
+impl Default for Backup {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            comics: vec![],
+            history: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupComic {
+    pub domain: String,
+    #[serde(default = "now")]
+    pub added_at: i64,
+    pub comic: Comic,
+    #[serde(default)]
+    pub progress: Vec<Progress>,
+}
+
+impl Backup {
+    pub fn from_json(json: &str) -> Result<Self> {
+        let backup: Self = serde_json::from_str(json)?;
+        if backup.schema_version > SCHEMA_VERSION {
+            return Err(err_msg(format!(
+                "Unsupported schema version {} (newer than {})",
+                backup.schema_version, SCHEMA_VERSION
+            )));
+        }
+
+        Ok(backup)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// 导入的结果。
+#[derive(Debug, Clone, Default)]
+pub struct Imported {
+    pub comics: usize,
+    pub progress: usize,
+    pub history: usize,
+    /// 无法导入的漫画 `(标题, 原因)`
+    pub skipped: Vec<(String, String)>,
+}
+
+impl Library {
+    /// 导出整个书架（包括阅读进度和历史记录）。
+    pub fn export(&self) -> Result<Backup> {
+        let mut backup = Backup::default();
+        for entry in self.list()? {
+            let progress = self.progress(&entry.domain, &entry.comic.url)?;
+            backup.comics.push(BackupComic {
+                domain: entry.domain,
+                added_at: entry.added_at,
+                comic: entry.comic,
+                progress,
+            });
+        }
+        backup.history = self.history(i64::MAX as usize)?;
+
+        Ok(backup)
+    }
+
+    pub fn export_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.export()?.to_json()?)?;
+        Ok(())
+    }
+
+    /// 导入备份，已存在的漫画会被合并。
+    pub fn import(&mut self, backup: &Backup) -> Result<Imported> {
+        let mut imported = Imported::default();
+        let tx = self.conn.transaction()?;
+        for item in &backup.comics {
+            let id = upsert_comic(&tx, &item.domain, &item.comic)?;
+            tx.execute(
+                "UPDATE comics SET added_at = MIN(added_at, ?1) WHERE id = ?2",
+                params![item.added_at, id],
+            )?;
+            for p in &item.progress {
+                upsert_progress(&tx, id, &p.chapter_url, p.state, p.updated_at)?;
+                imported.progress += 1;
+            }
+            imported.comics += 1;
+        }
+        for item in &backup.history {
+            let id: Option<i64> = tx
+                .query_row(
+                    "SELECT id FROM comics WHERE domain = ?1 AND url = ?2",
+                    params![item.domain, item.comic_url],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let id = match id {
+                Some(id) => id,
+                None => continue,
+            };
+            imported.history += tx.execute(
+                "INSERT INTO history (comic_id, chapter_url, chapter_title, page, read_at)
+                 SELECT ?1, ?2, ?3, ?4, ?5 WHERE NOT EXISTS (
+                    SELECT 1 FROM history WHERE comic_id = ?1 AND chapter_url = ?2 AND read_at = ?5
+                 )",
+                params![id, item.chapter_url, item.chapter_title, item.page as i64, item.read_at],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(imported)
+    }
+
+    pub fn import_from<P: AsRef<Path>>(&mut self, path: P) -> Result<Imported> {
+        self.import(&Backup::from_json(&fs::read_to_string(path)?)?)
+    }
+}
+
+#[test]
+fn test_backup() {
+    use super::reading::ReadingState;
+
+    let mut library = Library::open_in_memory().unwrap();
+    let domain = "www.manhuagui.com";
+    let mut comic = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    comic.push_chapter(Chapter::new("第1话", "https://www.manhuagui.com/comic/2863/1.html", 1));
+    comic.push_chapter(Chapter::new("第2话", "https://www.manhuagui.com/comic/2863/2.html", 2));
+    library.add(domain, &comic).unwrap();
+    library
+        .set_reading_state(domain, &comic.url, &comic.chapters[0], ReadingState::Read)
+        .unwrap();
+    library.record_reading(domain, &comic.url, &comic.chapters[1], 3).unwrap();
+
+    let json = library.export().unwrap().to_json().unwrap();
+    assert!(json.contains(r#""state": "in_progress""#));
+    let backup = Backup::from_json(&json).unwrap();
+    assert_eq!(1, backup.comics.len());
+    assert_eq!(2, backup.comics[0].progress.len());
+
+    let mut other = Library::open_in_memory().unwrap();
+    let imported = other.import(&backup).unwrap();
+    assert_eq!((1, 2, 1), (imported.comics, imported.progress, imported.history));
+    assert_eq!(
+        ReadingState::InProgress { page: 3 },
+        other.reading_state(domain, &comic.url, &comic.chapters[1]).unwrap()
+    );
+    assert_eq!(2, other.get(domain, &comic.url).unwrap().unwrap().comic.chapters.len());
+    // 重复导入不会产生重复的历史记录
+    assert_eq!(0, other.import(&backup).unwrap().history);
+    assert_eq!(1, other.history(10).unwrap().len());
+
+    assert!(Backup::from_json(r#"{"schema_version": 2, "comics": []}"#).is_err());
+}
@@ -0,0 +1,154 @@
+use super::reading::{upsert_progress, Progress};
+use super::*;
+use crate::extractors::get_extr;
+use crate::helper::normalize::normalize_title;
+use crate::matching::{Matcher, Matches};
+use crate::updates::{match_chapters, stable_id};
+
+/// 迁移的结果。
+#[derive(Debug, Clone, Default)]
+pub struct Migration {
+    /// 迁移后的漫画在书架中的 id
+    pub id: i64,
+    /// 成功迁移的阅读进度数量
+    pub migrated: usize,
+    /// 在新来源中找不到对应章节的阅读进度
+    pub unmapped: Vec<Progress>,
+}
+
+/// 将旧来源的章节映射到新来源，返回 `new` 中每个章节在 `old` 中对应的位置。
+///
+/// 不同来源的章节地址没有关联，依次按卷数和话数、话数、规范化后的标题匹配，
+/// 键只在两边都唯一时才用于匹配。
+pub fn map_chapters(old: &[Chapter], new: &[Chapter]) -> Vec<Option<usize>> {
+    match_chapters(
+        old,
+        new,
+        &[
+            |c| {
+                c.number()
+                    .map(|n| format!("{}/{}", c.volume().unwrap_or(0.0), n))
+            },
+            |c| c.number().map(|n| n.to_string()),
+            |c| Some(normalize_title(&c.title)).filter(|t| !t.is_empty()),
+        ],
+    )
+}
+
+impl Library {
+    /// 在其它来源中搜索书架中的漫画，作为迁移的候选。
+    pub fn migration_candidates(&self, domain: &str, url: &str, matcher: &Matcher) -> Result<Matches> {
+        let entry = self
+            .get(domain, url)?
+            .ok_or_else(|| err_msg(format!("Comic not found in library: {}", url)))?;
+
+        Ok(matcher.find(domain, &entry.comic))
+    }
+
+    /// 将书架中的漫画迁移到新来源 `new_domain` 的 `comic`（通常来自 `migration_candidates`）。
+    ///
+    /// `comic.chapters` 为空时会先获取章节列表。阅读进度按章节映射（见 `map_chapters`）到新来源，
+    /// 阅读历史和加入书架的时间会保留，旧的漫画随后从书架中移除。
+    pub fn migrate(&mut self, domain: &str, url: &str, new_domain: &str, comic: &Comic) -> Result<Migration> {
+        if domain == new_domain && url == comic.url {
+            return Err(err_msg("Cannot migrate a comic to itself"));
+        }
+        let entry = self
+            .get(domain, url)?
+            .ok_or_else(|| err_msg(format!("Comic not found in library: {}", url)))?;
+        let mut comic = comic.clone();
+        if comic.chapters.is_empty() {
+            let extr = get_extr(new_domain).ok_or_else(|| err_msg(format!("Unsupported domain: {}", new_domain)))?;
+            extr.fetch_chapters(&mut comic)?;
+        }
+        let mapping = map_chapters(&entry.comic.chapters, &comic.chapters);
+        let mut targets = HashMap::new();
+        for (j, i) in mapping.iter().enumerate() {
+            if let Some(i) = i {
+                targets.insert(stable_id(&entry.comic.chapters[*i].url), &comic.chapters[j]);
+            }
+        }
+
+        let mut migration = Migration::default();
+        let progress = self.progress(domain, url)?;
+        let tx = self.conn.transaction()?;
+        let id = upsert_comic(&tx, new_domain, &comic)?;
+        for p in progress {
+            let chapter = match targets.get(&stable_id(&p.chapter_url)) {
+                Some(chapter) => chapter,
+                None => {
+                    migration.unmapped.push(p);
+                    continue;
+                }
+            };
+            upsert_progress(&tx, id, &chapter.url, p.state, p.updated_at)?;
+            migration.migrated += 1;
+        }
+        tx.execute("UPDATE history SET comic_id = ?1 WHERE comic_id = ?2", params![id, entry.id])?;
+        tx.execute(
+            "UPDATE comics SET added_at = MIN(added_at, ?1) WHERE id = ?2",
+            params![entry.added_at, id],
+        )?;
+        tx.execute("DELETE FROM comics WHERE id = ?1", params![entry.id])?;
+        tx.commit()?;
+        migration.id = id;
+
+        Ok(migration)
+    }
+}
+
+#[test]
+fn test_migrate() {
+    use super::reading::ReadingState;
+
+    let mut library = Library::open_in_memory().unwrap();
+    let (domain, new_domain) = ("www.manhuagui.com", "www.dm5.com");
+    let mut comic = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    for (which, title) in ["第1话", "第2话", "第3话", "番外"].iter().enumerate() {
+        comic.push_chapter(Chapter::new(
+            *title,
+            format!("https://www.manhuagui.com/comic/2863/{}.html", which),
+            which as u32 + 1,
+        ));
+    }
+    library.add(domain, &comic).unwrap();
+    let states = [
+        ReadingState::Read,
+        ReadingState::Read,
+        ReadingState::InProgress { page: 5 },
+        ReadingState::Read,
+    ];
+    for (chapter, state) in comic.chapters.iter().zip(states.iter()) {
+        library.set_reading_state(domain, &comic.url, chapter, *state).unwrap();
+    }
+    library.record_reading(domain, &comic.url, &comic.chapters[2], 5).unwrap();
+
+    // 新来源的标题格式不同，并且多出了 `第0话`
+    let mut new_comic = Comic::new("食戟之靈", "https://www.dm5.com/manhua-shijizhiling/");
+    for (which, title) in ["第0话 序章", "第01话 出发", "第02话", "第03话 料理"].iter().enumerate() {
+        new_comic.push_chapter(Chapter::new(
+            *title,
+            format!("https://www.dm5.com/m{}/", which),
+            which as u32 + 1,
+        ));
+    }
+    let mapping = map_chapters(&comic.chapters, &new_comic.chapters);
+    assert_eq!(vec![None, Some(0), Some(1), Some(2)], mapping);
+
+    let migration = library.migrate(domain, &comic.url, new_domain, &new_comic).unwrap();
+    assert_eq!(3, migration.migrated);
+    assert_eq!(1, migration.unmapped.len());
+    assert!(!library.contains(domain, &comic.url).unwrap());
+    let state = library
+        .reading_state(new_domain, &new_comic.url, &new_comic.chapters[3])
+        .unwrap();
+    assert_eq!(ReadingState::InProgress { page: 5 }, state);
+    assert_eq!(
+        "第03话 料理",
+        library.next_unread(new_domain, &new_comic.url).unwrap().unwrap().title
+    );
+    let history = library.history(10).unwrap();
+    assert_eq!(1, history.len());
+    assert_eq!(new_comic.url, history[0].comic_url);
+    assert!(library.migrate(domain, &comic.url, new_domain, &new_comic).is_err());
+}
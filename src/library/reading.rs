@@ -0,0 +1,267 @@
+use super::*;
+use crate::updates::stable_id;
+use serde::{Deserialize, Serialize};
+
+/// 章节的阅读状态。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ReadingState {
+    Unread,
+    /// 阅读中，`page` 为最后阅读的页码（从 1 开始）
+    InProgress { page: usize },
+    Read,
+}
+
+impl ReadingState {
+    fn to_row(self) -> (i64, i64) {
+        match self {
+            ReadingState::Unread => (0, 0),
+            ReadingState::InProgress { page } => (1, page as i64),
+            ReadingState::Read => (2, 0),
+        }
+    }
+
+    fn from_row(state: i64, page: i64) -> Self {
+        match state {
+            1 => ReadingState::InProgress { page: page as usize },
+            2 => ReadingState::Read,
+            _ => ReadingState::Unread,
+        }
+    }
+}
+
+/// 章节的阅读进度。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Progress {
+    pub chapter_url: String,
+    #[serde(flatten)]
+    pub state: ReadingState,
+    /// 最后更新的时间（Unix 时间戳，秒）
+    pub updated_at: i64,
+}
+
+/// 阅读历史记录。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryItem {
+    pub domain: String,
+    pub comic_url: String,
+    pub comic_title: String,
+    pub chapter_url: String,
+    pub chapter_title: String,
+    pub page: usize,
+    pub read_at: i64,
+}
+
+// 进度以章节地址的稳定 id 关联，刷新章节列表或更换域名后仍然有效
+impl Library {
+    fn comic_id(&self, domain: &str, comic_url: &str) -> Result<i64> {
+        self.id(domain, comic_url)?
+            .ok_or_else(|| err_msg(format!("Comic not found in library: {}", comic_url)))
+    }
+
+    /// 设置章节的阅读状态。
+    pub fn set_reading_state(&mut self, domain: &str, comic_url: &str, chapter: &Chapter, state: ReadingState) -> Result<()> {
+        let comic_id = self.comic_id(domain, comic_url)?;
+        upsert_progress(&self.conn, comic_id, &chapter.url, state, now())
+    }
+
+    /// 记录阅读到第 `page` 页（从 1 开始），同时写入历史记录。
+    ///
+    /// `page` 为章节最后一页时标记为已读。
+    pub fn record_reading(&mut self, domain: &str, comic_url: &str, chapter: &Chapter, page: usize) -> Result<()> {
+        let state = if !chapter.pages.is_empty() && page >= chapter.pages.len() {
+            ReadingState::Read
+        } else {
+            ReadingState::InProgress { page }
+        };
+        self.set_reading_state(domain, comic_url, chapter, state)?;
+        let comic_id = self.comic_id(domain, comic_url)?;
+        self.conn.execute(
+            "INSERT INTO history (comic_id, chapter_url, chapter_title, page, read_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![comic_id, chapter.url, chapter.title, page as i64, now()],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn reading_state(&self, domain: &str, comic_url: &str, chapter: &Chapter) -> Result<ReadingState> {
+        let comic_id = self.comic_id(domain, comic_url)?;
+        let state = self
+            .conn
+            .query_row(
+                "SELECT state, page FROM progress WHERE comic_id = ?1 AND chapter_key = ?2",
+                params![comic_id, stable_id(&chapter.url)],
+                |row| Ok(ReadingState::from_row(row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        Ok(state.unwrap_or(ReadingState::Unread))
+    }
+
+    /// 漫画全部章节的阅读进度（仅包含有记录的章节）。
+    pub fn progress(&self, domain: &str, comic_url: &str) -> Result<Vec<Progress>> {
+        let comic_id = self.comic_id(domain, comic_url)?;
+        let mut stmt = self
+            .conn
+            .prepare("SELECT chapter_url, state, page, updated_at FROM progress WHERE comic_id = ?1 ORDER BY updated_at")?;
+        let progress = stmt
+            .query_map(params![comic_id], |row| {
+                Ok(Progress {
+                    chapter_url: row.get(0)?,
+                    state: ReadingState::from_row(row.get(1)?, row.get(2)?),
+                    updated_at: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(progress)
+    }
+
+    /// 下一个要阅读的章节：最靠后的阅读中章节，或最靠后的已读章节之后的第一个未读章节。
+    pub fn next_unread(&self, domain: &str, comic_url: &str) -> Result<Option<Chapter>> {
+        let entry = match self.get(domain, comic_url)? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let states = self
+            .progress(domain, comic_url)?
+            .into_iter()
+            .map(|p| (stable_id(&p.chapter_url), p))
+            .collect::<HashMap<_, _>>();
+        let state = |chapter: &Chapter| {
+            states
+                .get(&stable_id(&chapter.url))
+                .map(|p| p.state)
+                .unwrap_or(ReadingState::Unread)
+        };
+        let chapters = entry.comic.chapters;
+        // 列表中最靠后的有阅读记录的章节
+        let last = chapters.iter().rposition(|c| state(c) != ReadingState::Unread);
+        let start = match last {
+            Some(i) if matches!(state(&chapters[i]), ReadingState::InProgress { .. }) => return Ok(Some(chapters[i].clone())),
+            Some(i) => i + 1,
+            None => 0,
+        };
+
+        Ok(chapters[start..]
+            .iter()
+            .find(|c| state(c) != ReadingState::Read)
+            .cloned())
+    }
+
+    /// 最近阅读的漫画（每部漫画只保留最后一条记录），用于“继续阅读”。
+    pub fn continue_reading(&self, limit: usize) -> Result<Vec<HistoryItem>> {
+        self.query_history(
+            "WHERE history.id IN (SELECT MAX(id) FROM history GROUP BY comic_id)",
+            limit,
+        )
+    }
+
+    /// 按时间倒序的阅读历史。
+    pub fn history(&self, limit: usize) -> Result<Vec<HistoryItem>> {
+        self.query_history("", limit)
+    }
+
+    pub fn clear_history(&mut self) -> Result<()> {
+        self.conn.execute("DELETE FROM history", params![])?;
+        Ok(())
+    }
+
+    fn query_history(&self, condition: &str, limit: usize) -> Result<Vec<HistoryItem>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT comics.domain, comics.url, comics.title, history.chapter_url, history.chapter_title, history.page, history.read_at
+             FROM history JOIN comics ON comics.id = history.comic_id {}
+             ORDER BY history.read_at DESC, history.id DESC LIMIT ?1",
+            condition
+        ))?;
+        let items = stmt
+            .query_map(params![limit as i64], |row| {
+                Ok(HistoryItem {
+                    domain: row.get(0)?,
+                    comic_url: row.get(1)?,
+                    comic_title: row.get(2)?,
+                    chapter_url: row.get(3)?,
+                    chapter_title: row.get(4)?,
+                    page: row.get::<_, i64>(5)? as usize,
+                    read_at: row.get(6)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(items)
+    }
+}
+
+/// 写入阅读进度，已有更新的记录时保留原有记录。
+pub(super) fn upsert_progress(
+    conn: &Connection,
+    comic_id: i64,
+    chapter_url: &str,
+    state: ReadingState,
+    updated_at: i64,
+) -> Result<()> {
+    let (state, page) = state.to_row();
+    conn.execute(
+        "INSERT INTO progress (comic_id, chapter_key, chapter_url, state, page, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT (comic_id, chapter_key) DO UPDATE SET
+            chapter_url = excluded.chapter_url, state = excluded.state,
+            page = excluded.page, updated_at = excluded.updated_at
+         WHERE excluded.updated_at >= progress.updated_at",
+        params![comic_id, stable_id(chapter_url), chapter_url, state, page, updated_at],
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_reading() {
+    let mut library = Library::open_in_memory().unwrap();
+    let domain = "www.manhuagui.com";
+    let mut comic = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    for which in 1..=3 {
+        comic.push_chapter(Chapter::new(
+            format!("第{}话", which),
+            format!("https://www.manhuagui.com/comic/2863/{}.html", which),
+            which,
+        ));
+    }
+    library.add(domain, &comic).unwrap();
+    library
+        .add(domain, &Comic::new("一拳超人", "https://www.manhuagui.com/comic/7580/"))
+        .unwrap();
+
+    let next = library.next_unread(domain, &comic.url).unwrap().unwrap();
+    assert_eq!("第1话", next.title);
+    library
+        .set_reading_state(domain, &comic.url, &comic.chapters[0], ReadingState::Read)
+        .unwrap();
+    let mut chapter = comic.chapters[1].clone();
+    chapter.push_page(Page::new(0, "1.jpg"));
+    chapter.push_page(Page::new(1, "2.jpg"));
+    library.record_reading(domain, &comic.url, &chapter, 1).unwrap();
+    assert_eq!(
+        ReadingState::InProgress { page: 1 },
+        library.reading_state(domain, &comic.url, &chapter).unwrap()
+    );
+    assert_eq!("第2话", library.next_unread(domain, &comic.url).unwrap().unwrap().title);
+    library.record_reading(domain, &comic.url, &chapter, 2).unwrap();
+    assert_eq!("第3话", library.next_unread(domain, &comic.url).unwrap().unwrap().title);
+
+    // 刷新章节列表（地址的域名发生变化）后进度仍然有效
+    let chapters = comic
+        .chapters
+        .iter()
+        .map(|c| Chapter::new(c.title.clone(), c.url.replace("www.", "tw."), c.which + 1))
+        .collect::<Vec<_>>();
+    library.set_chapters(domain, &comic.url, &chapters).unwrap();
+    assert_eq!(ReadingState::Read, library.reading_state(domain, &comic.url, &chapters[1]).unwrap());
+    assert_eq!("第3话", library.next_unread(domain, &comic.url).unwrap().unwrap().title);
+
+    assert_eq!(2, library.history(10).unwrap().len());
+    let items = library.continue_reading(10).unwrap();
+    assert_eq!(1, items.len());
+    assert_eq!(2, items[0].page);
+    library.clear_history().unwrap();
+    assert!(library.history(10).unwrap().is_empty());
+}
@@ -0,0 +1,410 @@
+//! 导入 Tachiyomi/Mihon 的备份（`.tachibk`/`.proto.gz`）。
+//!
+//! 备份为 gzip 压缩的 protobuf 消息，这里只解析需要的字段：
+//!
+//! ```protobuf
+//! message Backup { repeated BackupManga backupManga = 1; repeated BackupSource backupSources = 101; }
+//! message BackupSource { string name = 1; int64 sourceId = 2; }
+//! message BackupManga {
+//!   int64 source = 1; string url = 2; string title = 3; string artist = 4; string author = 5;
+//!   string description = 6; repeated string genre = 7; int32 status = 8; string thumbnailUrl = 9;
+//!   int64 dateAdded = 13; repeated BackupChapter chapters = 16; repeated BackupHistory history = 104;
+//! }
+//! message BackupChapter { string url = 1; string name = 2; bool read = 4; int64 lastPageRead = 6; int64 sourceOrder = 10; int64 lastModifiedAt = 11; }
+//! message BackupHistory { string url = 1; int64 lastRead = 2; }
+//! ```
+//!
+//! 只有与 mikack 共有的来源（见 `SOURCES`）会被导入，漫画地址通过 `domain_route` 映射到对应的域名。
+use super::backup::{Backup, BackupComic, Imported};
+use super::reading::{HistoryItem, Progress, ReadingState};
+use super::*;
+use crate::extractors::{domain_route, DomainRoute};
+use crate::helper::normalize::normalize_title;
+use flate2::read::GzDecoder;
+use std::fs;
+use std::io::Read;
+
+/// 共有的来源：规范化后的来源名称包含的关键字，以及补全相对地址的根地址。
+static SOURCES: &[(&str, &str)] = &[
+    ("manganelo", "https://manganelo.com"),
+    ("mangareader", "https://www.mangareader.net"),
+    ("ehentai", "https://e-hentai.org"),
+    ("nhentai", "https://nhentai.net"),
+    ("manhuagui", "https://www.manhuagui.com"),
+    ("漫画柜", "https://www.manhuagui.com"),
+    ("dmzj", "https://manhua.dmzj.com"),
+    ("动漫之家", "https://manhua.dmzj.com"),
+];
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+impl<'a> Value<'a> {
+    fn int(&self) -> i64 {
+        match self {
+            Value::Varint(n) => *n as i64,
+            _ => 0,
+        }
+    }
+
+    fn string(&self) -> String {
+        match self {
+            Value::Bytes(bytes) => String::from_utf8_lossy(bytes).to_string(),
+            _ => String::new(),
+        }
+    }
+
+    fn message(&self) -> Result<Vec<(u32, Value<'a>)>> {
+        match self {
+            Value::Bytes(bytes) => fields(bytes),
+            _ => Err(err_msg("Invalid backup: expected an embedded message")),
+        }
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data
+            .get(*pos)
+            .ok_or_else(|| err_msg("Invalid backup: truncated varint"))?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(err_msg("Invalid backup: varint too long"))
+}
+
+/// 解析 protobuf 消息的全部字段 `(字段编号, 值)`。
+fn fields(data: &[u8]) -> Result<Vec<(u32, Value<'_>)>> {
+    let mut fields = vec![];
+    let mut pos = 0;
+    while pos < data.len() {
+        let key = read_varint(data, &mut pos)?;
+        let (number, wire_type) = ((key >> 3) as u32, key & 0x7);
+        let value = match wire_type {
+            0 => Value::Varint(read_varint(data, &mut pos)?),
+            1 | 5 => {
+                pos += if wire_type == 1 { 8 } else { 4 };
+                Value::Fixed
+            }
+            2 => {
+                let len = read_varint(data, &mut pos)? as usize;
+                let bytes = data
+                    .get(pos..pos.saturating_add(len))
+                    .ok_or_else(|| err_msg("Invalid backup: truncated field"))?;
+                pos += len;
+                Value::Bytes(bytes)
+            }
+            _ => return Err(err_msg(format!("Invalid backup: unsupported wire type {}", wire_type))),
+        };
+        fields.push((number, value));
+    }
+    if pos > data.len() {
+        return Err(err_msg("Invalid backup: truncated field"));
+    }
+
+    Ok(fields)
+}
+
+fn absolute_url(base: &str, url: &str) -> String {
+    if url.starts_with("http://") || url.starts_with("https://") {
+        url.to_string()
+    } else if url.starts_with('/') {
+        format!("{}{}", base, url)
+    } else {
+        format!("{}/{}", base, url)
+    }
+}
+
+fn comic_state(status: i64) -> ComicState {
+    match status {
+        1 => ComicState::Ongoing,
+        // COMPLETED 和 PUBLISHING_FINISHED
+        2 | 4 => ComicState::Completed,
+        _ => ComicState::Unknown,
+    }
+}
+
+struct TachiyomiChapter {
+    chapter: Chapter,
+    read: bool,
+    last_page_read: i64,
+    source_order: i64,
+    updated_at: i64,
+}
+
+fn read_chapter(base: &str, value: &Value) -> Result<TachiyomiChapter> {
+    let mut chapter = TachiyomiChapter {
+        chapter: Chapter::new("", "", 0),
+        read: false,
+        last_page_read: 0,
+        source_order: 0,
+        updated_at: 0,
+    };
+    for (number, value) in value.message()? {
+        match number {
+            1 => chapter.chapter.url = absolute_url(base, &value.string()),
+            2 => chapter.chapter.title = value.string(),
+            4 => chapter.read = value.int() != 0,
+            6 => chapter.last_page_read = value.int(),
+            10 => chapter.source_order = value.int(),
+            11 => chapter.updated_at = value.int(),
+            _ => (),
+        }
+    }
+
+    Ok(chapter)
+}
+
+fn read_comic(base: &str, domain: &str, fields: &[(u32, Value)]) -> Result<(BackupComic, Vec<HistoryItem>)> {
+    let mut comic = Comic::new("", "");
+    let mut added_at = 0;
+    let mut chapters = vec![];
+    let mut history = vec![];
+    let mut artist = String::new();
+    for (number, value) in fields {
+        match number {
+            2 => comic.url = absolute_url(base, &value.string()),
+            3 => comic.title = value.string(),
+            4 => artist = value.string(),
+            5 => comic.author = value.string(),
+            6 => comic.description = value.string(),
+            7 => comic.tags.push(value.string()),
+            8 => comic.state = comic_state(value.int()),
+            9 => comic.cover = value.string(),
+            13 => added_at = value.int() / 1000,
+            16 => chapters.push(read_chapter(base, value)?),
+            104 => {
+                let mut url = String::new();
+                let mut read_at = 0;
+                for (number, value) in value.message()? {
+                    match number {
+                        1 => url = absolute_url(base, &value.string()),
+                        2 => read_at = value.int() / 1000,
+                        _ => (),
+                    }
+                }
+                history.push((url, read_at));
+            }
+            _ => (),
+        }
+    }
+    if comic.author.is_empty() {
+        comic.author = artist;
+    }
+
+    // 来源顺序从最新的章节开始
+    chapters.sort_by_key(|c| -c.source_order);
+    let mut progress = vec![];
+    for (i, c) in chapters.iter_mut().enumerate() {
+        c.chapter.which = i as u32 + 1;
+        let state = if c.read {
+            ReadingState::Read
+        } else if c.last_page_read > 0 {
+            ReadingState::InProgress {
+                page: c.last_page_read as usize + 1,
+            }
+        } else {
+            continue;
+        };
+        progress.push(Progress {
+            chapter_url: c.chapter.url.clone(),
+            state,
+            updated_at: if c.updated_at > 0 { c.updated_at } else { now() },
+        });
+    }
+    let history = history
+        .into_iter()
+        .filter_map(|(url, read_at)| {
+            let c = chapters.iter().find(|c| c.chapter.url == url)?;
+            Some(HistoryItem {
+                domain: domain.to_string(),
+                comic_url: comic.url.clone(),
+                comic_title: comic.title.clone(),
+                chapter_url: url,
+                chapter_title: c.chapter.title.clone(),
+                page: c.last_page_read as usize + 1,
+                read_at,
+            })
+        })
+        .collect();
+    comic.chapters = chapters.into_iter().map(|c| c.chapter).collect();
+
+    Ok((
+        BackupComic {
+            domain: domain.to_string(),
+            added_at: if added_at > 0 { added_at } else { now() },
+            comic,
+            progress,
+        },
+        history,
+    ))
+}
+
+/// 解析 Tachiyomi/Mihon 的备份（可以是 gzip 压缩的），返回转换后的备份和跳过的漫画 `(标题, 原因)`。
+pub fn read_backup(data: &[u8]) -> Result<(Backup, Vec<(String, String)>)> {
+    let mut decompressed = vec![];
+    let data = if data.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(data).read_to_end(&mut decompressed)?;
+        &decompressed[..]
+    } else {
+        data
+    };
+    let fields = fields(data)?;
+
+    let mut sources = HashMap::new();
+    for (_, value) in fields.iter().filter(|(number, _)| *number == 101) {
+        let (mut name, mut id) = (String::new(), 0);
+        for (number, value) in value.message()? {
+            match number {
+                1 => name = value.string(),
+                2 => id = value.int(),
+                _ => (),
+            }
+        }
+        sources.insert(id, name);
+    }
+
+    let mut backup = Backup::default();
+    let mut skipped = vec![];
+    for (_, value) in fields.iter().filter(|(number, _)| *number == 1) {
+        let manga = value.message()?;
+        let source = manga
+            .iter()
+            .find(|(number, _)| *number == 1)
+            .map(|(_, value)| value.int())
+            .unwrap_or(0);
+        let title = manga
+            .iter()
+            .find(|(number, _)| *number == 3)
+            .map(|(_, value)| value.string())
+            .unwrap_or_default();
+        let name = sources.get(&source).map(|name| normalize_title(name)).unwrap_or_default();
+        let base = match SOURCES.iter().find(|(key, _)| name.contains(key)) {
+            Some((_, base)) => *base,
+            None => {
+                let name = sources.get(&source).cloned().unwrap_or_else(|| source.to_string());
+                skipped.push((title, format!("Unsupported source: {}", name)));
+                continue;
+            }
+        };
+        let url = manga
+            .iter()
+            .find(|(number, _)| *number == 2)
+            .map(|(_, value)| absolute_url(base, &value.string()))
+            .unwrap_or_default();
+        let domain = match domain_route(&url) {
+            Some(DomainRoute::Comic(domain)) | Some(DomainRoute::Chapter(domain)) => domain,
+            None => {
+                skipped.push((title, format!("Unsupported url: {}", url)));
+                continue;
+            }
+        };
+        let (comic, mut history) = read_comic(base, &domain, &manga)?;
+        backup.comics.push(comic);
+        backup.history.append(&mut history);
+    }
+
+    Ok((backup, skipped))
+}
+
+impl Library {
+    /// 导入 Tachiyomi/Mihon 的备份文件，不支持的来源会记录在 `Imported::skipped` 中。
+    pub fn import_tachiyomi<P: AsRef<Path>>(&mut self, path: P) -> Result<Imported> {
+        let (backup, skipped) = read_backup(&fs::read(path)?)?;
+        let mut imported = self.import(&backup)?;
+        imported.skipped = skipped;
+
+        Ok(imported)
+    }
+}
+
+#[test]
+fn test_tachiyomi() {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    fn varint(mut n: u64, buf: &mut Vec<u8>) {
+        while n >= 0x80 {
+            buf.push((n as u8) | 0x80);
+            n >>= 7;
+        }
+        buf.push(n as u8);
+    }
+    fn int(number: u32, n: u64) -> Vec<u8> {
+        let mut buf = vec![];
+        varint((number << 3) as u64, &mut buf);
+        varint(n, &mut buf);
+        buf
+    }
+    fn bytes(number: u32, data: &[u8]) -> Vec<u8> {
+        let mut buf = vec![];
+        varint((number << 3 | 2) as u64, &mut buf);
+        varint(data.len() as u64, &mut buf);
+        buf.extend_from_slice(data);
+        buf
+    }
+    fn chapter(url: &str, name: &str, read: bool, last_page_read: u64, source_order: u64) -> Vec<u8> {
+        [
+            bytes(1, url.as_bytes()),
+            bytes(2, name.as_bytes()),
+            int(4, read as u64),
+            int(6, last_page_read),
+            int(10, source_order),
+        ]
+        .concat()
+    }
+
+    let manhuagui = [
+        int(1, 1001),
+        bytes(2, b"/comic/2863/"),
+        bytes(3, "食戟之灵".as_bytes()),
+        bytes(5, "附田祐斗".as_bytes()),
+        bytes(7, "美食".as_bytes()),
+        int(8, 2),
+        int(13, 1_600_000_000_000),
+        bytes(16, &chapter("/comic/2863/2.html", "第2话", false, 4, 0)),
+        bytes(16, &chapter("/comic/2863/1.html", "第1话", true, 0, 1)),
+        bytes(104, &[bytes(1, b"/comic/2863/2.html"), int(2, 1_600_000_100_000)].concat()),
+    ]
+    .concat();
+    let unknown = [int(1, 2002), bytes(2, b"/manga/1"), bytes(3, b"Unknown")].concat();
+    let data = [
+        bytes(1, &manhuagui),
+        bytes(1, &unknown),
+        bytes(101, &[bytes(1, "漫畫櫃".as_bytes()), int(2, 1001)].concat()),
+        bytes(101, &[bytes(1, b"MangaDex"), int(2, 2002)].concat()),
+    ]
+    .concat();
+    let mut encoder = GzEncoder::new(vec![], Compression::default());
+    encoder.write_all(&data).unwrap();
+    let data = encoder.finish().unwrap();
+
+    let (backup, skipped) = read_backup(&data).unwrap();
+    assert_eq!(vec![(String::from("Unknown"), String::from("Unsupported source: MangaDex"))], skipped);
+    assert_eq!(1, backup.comics.len());
+    let item = &backup.comics[0];
+    assert_eq!("www.manhuagui.com", item.domain);
+    assert_eq!(1_600_000_000, item.added_at);
+    assert_eq!("https://www.manhuagui.com/comic/2863/", item.comic.url);
+    assert!(matches!(item.comic.state, ComicState::Completed));
+    assert_eq!("第1话", item.comic.chapters[0].title);
+    assert_eq!("https://www.manhuagui.com/comic/2863/2.html", item.comic.chapters[1].url);
+    assert_eq!(2, item.progress.len());
+    assert_eq!(ReadingState::InProgress { page: 5 }, item.progress[1].state);
+    assert_eq!(1, backup.history.len());
+
+    let mut library = Library::open_in_memory().unwrap();
+    let imported = library.import(&backup).unwrap();
+    assert_eq!((1, 1), (imported.comics, imported.history));
+    let next = library.next_unread("www.manhuagui.com", &item.comic.url).unwrap().unwrap();
+    assert_eq!("第2话", next.title);
+    assert!(read_backup(&[0x0a, 0x05, 0x01]).is_err());
+}
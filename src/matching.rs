@@ -0,0 +1,173 @@
+use crate::extractors::{get_extr, platforms};
+use crate::helper::normalize::{normalize_title, similarity};
+use crate::{error::*, models::*};
+
+/// 其它来源中的候选漫画。
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub domain: String,
+    pub comic: Comic,
+    /// 匹配分数（0-1）
+    pub score: f64,
+}
+
+/// 匹配结果，搜索失败的来源记录在 `errors` 中。
+#[derive(Debug, Clone, Default)]
+pub struct Matches {
+    pub candidates: Vec<Candidate>,
+    pub errors: Vec<(String, String)>,
+}
+
+const TITLE_WEIGHT: f64 = 0.7;
+const AUTHOR_WEIGHT: f64 = 0.2;
+const CHAPTERS_WEIGHT: f64 = 0.1;
+
+/// 计算候选漫画与目标漫画的匹配分数（0-1）。
+///
+/// 标题、作者和章节数分别按权重计分，缺少作者或章节信息时只按已有的信息计分。
+pub fn score(target: &Comic, candidate: &Comic) -> f64 {
+    let mut total = TITLE_WEIGHT * similarity(&normalize_title(&target.title), &normalize_title(&candidate.title));
+    let mut weights = TITLE_WEIGHT;
+    let (author, other_author) = (normalize_title(&target.author), normalize_title(&candidate.author));
+    if !author.is_empty() && !other_author.is_empty() {
+        let author_score = if author.contains(&other_author) || other_author.contains(&author) {
+            1.0
+        } else {
+            similarity(&author, &other_author)
+        };
+        total += AUTHOR_WEIGHT * author_score;
+        weights += AUTHOR_WEIGHT;
+    }
+    let (count, other_count) = (target.chapters.len(), candidate.chapters.len());
+    if count > 0 && other_count > 0 {
+        total += CHAPTERS_WEIGHT * count.min(other_count) as f64 / count.max(other_count) as f64;
+        weights += CHAPTERS_WEIGHT;
+    }
+
+    total / weights
+}
+
+/// 在其它来源中搜索同一部漫画。
+pub struct Matcher {
+    domains: Vec<String>,
+    pages: u32,
+    min_score: f64,
+    fetch_chapters: bool,
+}
+
+impl Default for Matcher {
+    fn default() -> Self {
+        Self {
+            domains: platforms().keys().cloned().collect(),
+            pages: 1,
+            min_score: 0.6,
+            fetch_chapters: false,
+        }
+    }
+}
+
+impl Matcher {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// 参与搜索的来源，默认为全部来源。
+    pub fn domains(mut self, domains: Vec<String>) -> Self {
+        self.domains = domains;
+        self
+    }
+
+    /// 每个来源搜索的页数（支持分页搜索时）。
+    pub fn pages(mut self, pages: u32) -> Self {
+        self.pages = pages.max(1);
+        self
+    }
+
+    /// 低于此分数的候选会被丢弃。
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// 获取候选漫画的章节列表以参与计分（需要额外的请求）。
+    pub fn fetch_chapters(mut self, fetch_chapters: bool) -> Self {
+        self.fetch_chapters = fetch_chapters;
+        self
+    }
+
+    /// 搜索除 `source` 以外的来源，按分数从高到低返回候选。
+    pub fn find(&self, source: &str, comic: &Comic) -> Matches {
+        let mut matches = Matches::default();
+        for domain in self.domains.iter().filter(|d| *d != source) {
+            match self.find_in(domain, comic) {
+                Ok(mut candidates) => matches.candidates.append(&mut candidates),
+                Err(e) => matches.errors.push((domain.clone(), e.to_string())),
+            }
+        }
+        sort_candidates(&mut matches.candidates);
+
+        matches
+    }
+
+    fn find_in(&self, domain: &str, comic: &Comic) -> Result<Vec<Candidate>> {
+        let extr = get_extr(domain).ok_or_else(|| err_msg(format!("Unsupported domain: {}", domain)))?;
+        if !extr.is_searchable() {
+            return Ok(vec![]);
+        }
+        let pages = if extr.is_pageable_search() { self.pages } else { 1 };
+        let mut candidates = vec![];
+        for page in 1..=pages {
+            let results = extr.paginated_search(&comic.title, page)?;
+            if results.is_empty() {
+                break;
+            }
+            for mut result in results {
+                if self.fetch_chapters
+                    && !comic.chapters.is_empty()
+                    && result.chapters.is_empty()
+                    && score(comic, &result) >= self.min_score
+                {
+                    // 获取失败时只按标题和作者计分
+                    let _ = extr.fetch_chapters(&mut result);
+                }
+                let score = score(comic, &result);
+                if score >= self.min_score {
+                    candidates.push(Candidate {
+                        domain: domain.to_string(),
+                        comic: result,
+                        score,
+                    });
+                }
+            }
+        }
+
+        Ok(candidates)
+    }
+}
+
+pub fn sort_candidates(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+#[test]
+fn test_score() {
+    let mut target = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    target.author = String::from("附田祐斗");
+    for which in 1..=300 {
+        target.push_chapter(Chapter::new("", "", which));
+    }
+
+    let mut same = Comic::new("食戟之靈", "https://www.dm5.com/manhua-shijizhiling/");
+    same.author = String::from("附田祐斗 佐伯俊");
+    assert!(score(&target, &same) > 0.95);
+    for which in 1..=150 {
+        same.push_chapter(Chapter::new("", "", which));
+    }
+    let with_chapters = score(&target, &same);
+    assert!(with_chapters > 0.9 && with_chapters < 0.96);
+
+    let mut other = Comic::new("食戟之灵 番外篇", "https://www.dm5.com/manhua-shijizhiling-fanwai/");
+    other.author = String::from("佐伯俊");
+    assert!(score(&target, &other) < score(&target, &same));
+    assert!(score(&target, &Comic::new("一拳超人", "")) < 0.1);
+}
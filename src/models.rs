@@ -1,10 +1,12 @@
+use crate::helper::{mime, numbering};
 use num_derive::FromPrimitive;
 use percent_encoding::{utf8_percent_encode, CONTROLS};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::{default::Default, path::Path};
+use std::default::Default;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Page {
     pub n: usize,
     pub address: String,
@@ -13,12 +15,15 @@ pub struct Page {
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Chapter {
     pub title: String,
     pub url: String,
     pub which: u32,
     pub pages: Vec<Page>,
     pub page_headers: HashMap<String, String>,
+    /// 所属分组的名称（例如单话、单行本），来源没有分组时为空
+    pub group: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +40,7 @@ impl Default for ComicState {
 }
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Comic {
     pub title: String,
     pub url: String,
@@ -95,30 +101,48 @@ def_tags![
     {NSFW: "NSFW"},
 ];
 
-static DEFAULT_MIME: &'static str = "*/*";
-
 impl Page {
     pub fn new<S: Into<String>>(n: usize, address: S) -> Self {
         let address = address.into();
-        let fname = Page::fname(&address.clone(), &n);
+        let fname = Page::fname(&address, &n);
+        let fmime = mime::from_address(&address).unwrap_or(mime::DEFAULT_MIME);
         Self {
             n,
             address: address,
             fname,
-            fmime: DEFAULT_MIME.to_string(),
+            fmime: fmime.to_string(),
         }
     }
 
     pub fn fname(address: &str, n: &usize) -> String {
         let mut name = n.to_string();
-        if let Some(extension) = Path::new(address).extension() {
-            if let Some(ext_s) = extension.to_str() {
-                let params_marker_index = ext_s.find("?").unwrap_or(extension.len());
-                name += &format!(".{}", ext_s[0..params_marker_index].to_string());
-            }
+        if let Some(extension) = mime::extension_from_address(address) {
+            name += &format!(".{}", extension);
         }
         name
     }
+
+    /// 更新类型并重写文件名的扩展名，无法识别的类型会被忽略。
+    pub fn set_mime(&mut self, mime: &str) -> &Self {
+        if let Some(extension) = mime::to_extension(mime) {
+            self.fmime = mime::from_extension(extension)
+                .unwrap_or(mime::DEFAULT_MIME)
+                .to_string();
+            let stem = self.fname.split('.').next().unwrap_or("").to_string();
+            let stem = if stem.is_empty() { self.n.to_string() } else { stem };
+            self.fname = format!("{}.{}", stem, extension);
+        }
+        self
+    }
+
+    /// 根据下载结果确定真实类型：优先文件头魔数，其次响应的 Content-Type，都无法识别时保留原值。
+    pub fn detect_mime(&mut self, content_type: Option<&str>, data: &[u8]) -> &Self {
+        let detected = mime::sniff(data).or(content_type.and_then(mime::from_content_type));
+        if let Some(mime) = detected {
+            self.set_mime(mime);
+        }
+        self
+    }
 }
 
 impl Chapter {
@@ -158,6 +182,16 @@ impl Chapter {
     pub fn push_page_header<S: Into<String>>(&mut self, key: S, value: S) {
         self.page_headers.insert(key.into(), value.into());
     }
+
+    /// 从标题中解析的话数（不同于表示列表位置的 `which`）。
+    pub fn number(&self) -> Option<f64> {
+        numbering::parse_chapter_number(&self.title)
+    }
+
+    /// 从标题中解析的卷数。
+    pub fn volume(&self) -> Option<f64> {
+        numbering::parse_volume_number(&self.title)
+    }
 }
 
 impl Comic {
@@ -224,6 +258,16 @@ impl FromUrl for Chapter {
     }
 }
 
+pub trait SetGroup {
+    fn set_group(&mut self, group: &str);
+}
+
+impl SetGroup for Chapter {
+    fn set_group(&mut self, group: &str) {
+        self.group = group.to_string()
+    }
+}
+
 impl SetWhich for Chapter {
     fn set_which(&mut self, which: usize) {
         self.which = which as u32
@@ -251,3 +295,20 @@ impl SetCover for Comic {
 impl SetCover for Chapter {
     fn set_cover<S: Into<String>>(&mut self, _address: S) {}
 }
+
+#[test]
+fn test_page_mime() {
+    let mut page = Page::new(1, "https://i.hamreus.com/ps3/s/001.jpg?e=1583660581&m=Xv4Gd");
+    assert_eq!("1.jpg", page.fname);
+    assert_eq!("image/jpeg", page.fmime);
+    page.detect_mime(Some("image/webp"), b"RIFF\x00\x00\x00\x00WEBPVP8 ");
+    assert_eq!("1.webp", page.fname);
+    assert_eq!("image/webp", page.fmime);
+
+    let mut page = Page::new(2, "https://cdn.example.com/image/8f3a2c");
+    assert_eq!("2", page.fname);
+    assert_eq!("*/*", page.fmime);
+    page.detect_mime(Some("image/png"), b"");
+    assert_eq!("2.png", page.fname);
+    assert_eq!("image/png", page.fmime);
+}
@@ -0,0 +1,262 @@
+use crate::{error::*, extractors::PageData};
+use image::{DynamicImage, GenericImageView, ImageOutputFormat, RgbaImage};
+
+/// 页面后处理器，在页面数据下载完成后执行（格式转换、切片还原、裁剪等）。
+///
+/// 处理器需要同步更新 `PageData.page` 的类型和文件名（参考 `Page::set_mime`）。
+pub trait Processor: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn process(&self, data: PageData) -> Result<PageData>;
+}
+
+pub type ProcessorObject = Box<dyn Processor>;
+
+/// 按顺序执行的处理器集合。
+#[derive(Default)]
+pub struct Pipeline {
+    processors: Vec<ProcessorObject>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn push<P: Processor + 'static>(mut self, processor: P) -> Self {
+        self.processors.push(Box::new(processor));
+        self
+    }
+
+    pub fn push_boxed(mut self, processor: ProcessorObject) -> Self {
+        self.processors.push(processor);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processors.is_empty()
+    }
+
+    pub fn run(&self, data: PageData) -> Result<PageData> {
+        run(&self.processors, data)
+    }
+}
+
+pub fn run(processors: &[ProcessorObject], mut data: PageData) -> Result<PageData> {
+    for processor in processors {
+        data = processor
+            .process(data)
+            .map_err(|e| err_msg(format!("Processor `{}` failed: {}", processor.name(), e)))?;
+    }
+
+    Ok(data)
+}
+
+/// 输出的图片格式。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Jpeg(u8),
+    Png,
+}
+
+impl Format {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Format::Jpeg(_) => "image/jpeg",
+            Format::Png => "image/png",
+        }
+    }
+}
+
+pub fn decode(data: &PageData) -> Result<DynamicImage> {
+    Ok(image::load_from_memory(&data.bytes)?)
+}
+
+/// 将图片编码为指定格式，并更新页面的类型和文件名。
+pub fn encode(mut data: PageData, image: &DynamicImage, format: Format) -> Result<PageData> {
+    let mut bytes = vec![];
+    match format {
+        Format::Jpeg(quality) => {
+            // JPEG 不支持透明通道
+            DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_to(&mut bytes, ImageOutputFormat::Jpeg(quality))?
+        }
+        Format::Png => image.write_to(&mut bytes, ImageOutputFormat::Png)?,
+    };
+    data.bytes = bytes;
+    data.page.set_mime(format.mime());
+
+    Ok(data)
+}
+
+/// 将指定类型的图片转换为目标格式，例如将 WebP 转换为 JPEG。
+pub struct ConvertFormat {
+    pub from: Vec<String>,
+    pub to: Format,
+}
+
+impl ConvertFormat {
+    pub fn new(from: &[&str], to: Format) -> Self {
+        Self {
+            from: from.iter().map(|m| m.to_string()).collect(),
+            to,
+        }
+    }
+
+    pub fn webp(to: Format) -> Self {
+        Self::new(&["image/webp"], to)
+    }
+}
+
+impl Processor for ConvertFormat {
+    fn name(&self) -> &str {
+        "convert_format"
+    }
+
+    fn process(&self, data: PageData) -> Result<PageData> {
+        if !self.from.contains(&data.page.fmime) {
+            return Ok(data);
+        }
+        let image = decode(&data)?;
+        encode(data, &image, self.to)
+    }
+}
+
+/// 裁剪图片四周颜色一致的边缘。
+pub struct Trim {
+    /// 允许的颜色误差（0-255）
+    pub tolerance: u8,
+    pub format: Format,
+}
+
+impl Default for Trim {
+    fn default() -> Self {
+        Self {
+            tolerance: 16,
+            format: Format::Jpeg(90),
+        }
+    }
+}
+
+impl Processor for Trim {
+    fn name(&self) -> &str {
+        "trim"
+    }
+
+    fn process(&self, data: PageData) -> Result<PageData> {
+        let image = decode(&data)?;
+        let (x, y, width, height) = content_bounds(&image, self.tolerance);
+        if (width, height) == image.dimensions() {
+            return Ok(data);
+        }
+        encode(data, &image.crop_imm(x, y, width, height), self.format)
+    }
+}
+
+/// 计算去掉统一边缘后的内容区域 `(x, y, width, height)`，以左上角像素颜色为边缘颜色。
+pub fn content_bounds(image: &DynamicImage, tolerance: u8) -> (u32, u32, u32, u32) {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return (0, 0, width, height);
+    }
+    let gray = image.to_luma8();
+    let background = gray.get_pixel(0, 0)[0] as i32;
+    let is_blank = |x: u32, y: u32| (gray.get_pixel(x, y)[0] as i32 - background).abs() <= tolerance as i32;
+    let row_blank = |y: u32| (0..width).all(|x| is_blank(x, y));
+    let column_blank = |x: u32, top: u32, bottom: u32| (top..bottom).all(|y| is_blank(x, y));
+
+    let top = (0..height).find(|y| !row_blank(*y));
+    let top = match top {
+        Some(top) => top,
+        // 整张图片都是空白
+        None => return (0, 0, width, height),
+    };
+    let bottom = (0..height).rev().find(|y| !row_blank(*y)).unwrap_or(top) + 1;
+    let left = (0..width).find(|x| !column_blank(*x, top, bottom)).unwrap_or(0);
+    let right = (0..width)
+        .rev()
+        .find(|x| !column_blank(*x, top, bottom))
+        .unwrap_or(width - 1)
+        + 1;
+
+    (left, top, right - left, bottom - top)
+}
+
+type TileOrder = Box<dyn Fn(&PageData) -> Result<Vec<usize>> + Send + Sync>;
+
+/// 还原被打乱的切片图片。
+///
+/// 图片被均分为 `columns` x `rows` 个切片，`order` 返回每个目标位置（按行优先）对应的原图切片序号。
+pub struct Untile {
+    pub columns: u32,
+    pub rows: u32,
+    pub order: TileOrder,
+    pub format: Format,
+}
+
+impl Processor for Untile {
+    fn name(&self) -> &str {
+        "untile"
+    }
+
+    fn process(&self, data: PageData) -> Result<PageData> {
+        let order = (self.order)(&data)?;
+        let count = (self.columns * self.rows) as usize;
+        if order.len() != count || order.iter().any(|i| *i >= count) {
+            return Err(err_msg(format!("Invalid tile order: {:?}", order)));
+        }
+        let image = decode(&data)?;
+        let (width, height) = image.dimensions();
+        let (tile_width, tile_height) = (width / self.columns, height / self.rows);
+        let mut output = RgbaImage::new(width, height);
+        // 无法整除的剩余部分保持原位
+        image::imageops::replace(&mut output, &image.to_rgba8(), 0, 0);
+        for (target, source) in order.iter().enumerate() {
+            let (source, target) = (*source as u32, target as u32);
+            let tile = image.crop_imm(
+                (source % self.columns) * tile_width,
+                (source / self.columns) * tile_height,
+                tile_width,
+                tile_height,
+            );
+            image::imageops::replace(
+                &mut output,
+                &tile.to_rgba8(),
+                (target % self.columns) * tile_width,
+                (target / self.columns) * tile_height,
+            );
+        }
+        encode(data, &DynamicImage::ImageRgba8(output), self.format)
+    }
+}
+
+#[test]
+fn test_convert_and_trim() {
+    use crate::models::Page;
+    use image::{Rgb, RgbImage};
+
+    let mut image = RgbImage::from_pixel(20, 10, Rgb([255, 255, 255]));
+    for x in 5..15 {
+        for y in 2..8 {
+            image.put_pixel(x, y, Rgb([0, 0, 0]));
+        }
+    }
+    let mut bytes = vec![];
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut bytes, ImageOutputFormat::Png)
+        .unwrap();
+    let data = PageData::new(&Page::new(1, "https://example.com/1"), None, bytes);
+    assert_eq!("1.png", data.page.fname);
+
+    let pipeline = Pipeline::new()
+        .push(ConvertFormat::new(&["image/png"], Format::Png))
+        .push(Trim {
+            tolerance: 0,
+            format: Format::Jpeg(90),
+        });
+    let data = pipeline.run(data).unwrap();
+    assert_eq!("1.jpg", data.page.fname);
+    assert_eq!("image/jpeg", data.page.fmime);
+    let trimmed = decode(&data).unwrap();
+    assert_eq!((10, 6), trimmed.dimensions());
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+/// 页面总数。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Total {
+    Known(usize),
+    /// 总数未知，需要持续解析直到没有更多页面
+    Unknown,
+}
+
+impl Total {
+    pub fn known(&self) -> Option<usize> {
+        match self {
+            Total::Known(total) => Some(*total),
+            Total::Unknown => None,
+        }
+    }
+}
+
+/// 页面解析和下载过程中产生的事件，页码 `n` 均从 1 开始。
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// 开始解析章节页面
+    Discovering { url: String },
+    /// 章节解析完成，已知页面总数（或未知）
+    Discovered { total: Total },
+    PageResolved { n: usize, total: Total },
+    PageDownloaded { n: usize, size: usize },
+    /// 第 `attempt` 次重试前触发
+    Retrying { n: usize, attempt: usize, error: String },
+    Failed { n: usize, error: String },
+    /// 章节解析失败，没有对应的页面
+    ChapterFailed { url: String, error: String },
+}
+
+pub type Listener = Arc<dyn Fn(&Event) + Send + Sync>;
+
+pub fn listener<F: Fn(&Event) + Send + Sync + 'static>(f: F) -> Listener {
+    Arc::new(f)
+}
+
+pub fn silent() -> Listener {
+    Arc::new(|_| {})
+}
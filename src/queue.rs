@@ -0,0 +1,402 @@
+use crate::download::{self, Downloader, Selection};
+use crate::extractors::{domain_route, get_extr, DomainRoute};
+use crate::progress::{silent, Listener};
+use crate::{error::*, models::*};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+/// 下载任务的状态。
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum JobState {
+    Pending,
+    Running,
+    Paused,
+    Failed { error: String },
+    Done,
+}
+
+/// 一个章节的下载任务。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    /// 来源域名，用于选择提取器和限制并发
+    pub domain: String,
+    pub chapter: Chapter,
+    pub dir: PathBuf,
+    pub state: JobState,
+}
+
+/// 保存在磁盘上的下载队列（JSON），每次变更后立即写入。
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Queue {
+    #[serde(skip)]
+    path: PathBuf,
+    next_id: u64,
+    jobs: Vec<Job>,
+}
+
+impl Queue {
+    /// 打开队列文件，文件不存在时创建空队列。
+    ///
+    /// 上次退出时仍在运行的任务会恢复为等待状态。
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut queue = if path.exists() {
+            serde_json::from_slice::<Queue>(&fs::read(&path)?)?
+        } else {
+            Queue {
+                path: PathBuf::new(),
+                next_id: 1,
+                jobs: vec![],
+            }
+        };
+        queue.path = path;
+        for job in queue.jobs.iter_mut() {
+            if job.state == JobState::Running {
+                job.state = JobState::Pending;
+            }
+        }
+
+        Ok(queue)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let dir = self.path.parent().unwrap_or_else(|| Path::new("."));
+        let fname = self
+            .path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| err_msg(format!("Invalid queue path: {:?}", self.path)))?;
+        fs::create_dir_all(dir)?;
+        download::save(dir, fname, &serde_json::to_vec_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    /// 添加章节任务，来源域名由章节地址匹配（见 `domain_route`），不一定与地址中的主机名相同。
+    pub fn push(&mut self, chapter: Chapter, dir: PathBuf) -> Result<u64> {
+        let domain = match domain_route(&chapter.url) {
+            Some(DomainRoute::Chapter(domain)) | Some(DomainRoute::Comic(domain)) => domain,
+            None => return Err(err_msg(format!("Unsupported url: {}", chapter.url))),
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs.push(Job {
+            id,
+            domain,
+            chapter,
+            dir,
+            state: JobState::Pending,
+        });
+        self.save()?;
+
+        Ok(id)
+    }
+
+    /// 添加漫画中被选中的章节，目录布局同 `Downloader::download_comic`，已在队列中的章节会被忽略。
+    pub fn push_comic(&mut self, comic: &Comic, selection: &Selection, root: &Path) -> Result<Vec<u64>> {
+        let comic_dir = download::comic_dir(root, comic);
+        let mut ids = vec![];
+        for i in selection.select(&comic.chapters) {
+            let chapter = &comic.chapters[i];
+            if self.jobs.iter().any(|job| job.chapter.url == chapter.url) {
+                continue;
+            }
+            let dir = download::chapter_dir(&comic_dir, chapter);
+            ids.push(self.push(chapter.clone(), dir)?);
+        }
+
+        Ok(ids)
+    }
+
+    /// 暂停等待中或运行中的任务，运行中的任务会在当前章节结束后停止。
+    pub fn pause(&mut self, id: u64) -> Result<()> {
+        self.transit(id, |state| match state {
+            JobState::Pending | JobState::Running => Some(JobState::Paused),
+            _ => None,
+        })
+    }
+
+    /// 恢复暂停或失败的任务。
+    pub fn resume(&mut self, id: u64) -> Result<()> {
+        self.transit(id, |state| match state {
+            JobState::Paused | JobState::Failed { .. } => Some(JobState::Pending),
+            _ => None,
+        })
+    }
+
+    pub fn remove(&mut self, id: u64) -> Result<Option<Job>> {
+        let job = self
+            .jobs
+            .iter()
+            .position(|job| job.id == id)
+            .map(|i| self.jobs.remove(i));
+        self.save()?;
+
+        Ok(job)
+    }
+
+    /// 移除已完成的任务。
+    pub fn clear_done(&mut self) -> Result<()> {
+        self.jobs.retain(|job| job.state != JobState::Done);
+        self.save()
+    }
+
+    fn transit<F: Fn(&JobState) -> Option<JobState>>(&mut self, id: u64, f: F) -> Result<()> {
+        let job = self
+            .jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| err_msg(format!("Job `{}` not found", id)))?;
+        let state = f(&job.state)
+            .ok_or_else(|| err_msg(format!("Job `{}` is {:?}", id, job.state)))?;
+        job.state = state;
+        self.save()
+    }
+
+    fn has_pending(&self) -> bool {
+        self.jobs.iter().any(|job| job.state == JobState::Pending)
+    }
+
+    // 取出第一个所在域名未达到并发上限的等待任务，并标记为运行中
+    fn take_next(&mut self, running: &HashMap<String, usize>, per_domain: usize) -> Result<Option<Job>> {
+        let job = self.jobs.iter_mut().find(|job| {
+            job.state == JobState::Pending && running.get(&job.domain).copied().unwrap_or(0) < per_domain
+        });
+        match job {
+            Some(job) => {
+                job.state = JobState::Running;
+                let job = job.clone();
+                self.save()?;
+                Ok(Some(job))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // 写回任务结果，任务在运行期间被暂停或移除时保留当前状态
+    fn finish(&mut self, job: Job, state: JobState) -> Result<()> {
+        if let Some(current) = self.jobs.iter_mut().find(|j| j.id == job.id) {
+            current.chapter = job.chapter;
+            if current.state == JobState::Running || state == JobState::Done {
+                current.state = state;
+            }
+        }
+        self.save()
+    }
+}
+
+struct Scheduler {
+    queue: Mutex<Queue>,
+    running: Mutex<HashMap<String, usize>>,
+    changed: Condvar,
+}
+
+/// 按队列调度下载的管理器，同一来源的任务数不超过 `per_domain`。
+///
+/// 队列可以在运行期间通过 `queue()` 修改（添加、暂停、恢复任务）。
+pub struct Manager {
+    scheduler: Scheduler,
+    concurrency: usize,
+    per_domain: usize,
+    page_concurrency: usize,
+    listener: Listener,
+    stopped: AtomicBool,
+}
+
+impl Manager {
+    pub fn new(queue: Queue) -> Self {
+        Self {
+            scheduler: Scheduler {
+                queue: Mutex::new(queue),
+                running: Mutex::new(HashMap::new()),
+                changed: Condvar::new(),
+            },
+            concurrency: 4,
+            per_domain: 1,
+            page_concurrency: 4,
+            listener: silent(),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// 同时下载的章节数。
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 同一来源同时下载的章节数，默认为 1。
+    pub fn per_domain(mut self, per_domain: usize) -> Self {
+        self.per_domain = per_domain.max(1);
+        self
+    }
+
+    /// 每个章节内同时下载的页面数。
+    pub fn page_concurrency(mut self, page_concurrency: usize) -> Self {
+        self.page_concurrency = page_concurrency.max(1);
+        self
+    }
+
+    pub fn listener(mut self, listener: Listener) -> Self {
+        self.listener = listener;
+        self
+    }
+
+    /// 访问队列，修改后会唤醒等待中的工作线程。
+    pub fn queue<R, F: FnOnce(&mut Queue) -> R>(&self, f: F) -> R {
+        let result = f(&mut self.scheduler.queue.lock().unwrap());
+        self.scheduler.changed.notify_all();
+        result
+    }
+
+    /// 停止开始新的任务，运行中的任务会继续直到当前章节结束。
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.scheduler.changed.notify_all();
+    }
+
+    /// 执行队列中的任务，直到没有等待中的任务或被停止。
+    pub fn run(&self) -> Result<()> {
+        let errors = Mutex::new(vec![]);
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency {
+                scope.spawn(|| {
+                    if let Err(e) = self.work() {
+                        errors.lock().unwrap().push(e);
+                    }
+                    self.scheduler.changed.notify_all();
+                });
+            }
+        });
+
+        match errors.into_inner().unwrap().pop() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn work(&self) -> Result<()> {
+        let scheduler = &self.scheduler;
+        loop {
+            let job = {
+                let mut queue = scheduler.queue.lock().unwrap();
+                loop {
+                    if self.stopped.load(Ordering::SeqCst) {
+                        return Ok(());
+                    }
+                    let mut running = scheduler.running.lock().unwrap();
+                    if let Some(job) = queue.take_next(&running, self.per_domain)? {
+                        *running.entry(job.domain.clone()).or_insert(0) += 1;
+                        break job;
+                    }
+                    let idle = running.values().all(|n| *n == 0);
+                    drop(running);
+                    if !queue.has_pending() && idle {
+                        return Ok(());
+                    }
+                    // 等待其它任务结束或队列发生变化
+                    queue = scheduler.changed.wait(queue).unwrap();
+                }
+            };
+
+            let domain = job.domain.clone();
+            let (job, state) = self.execute(job);
+            // 持有队列锁时更新计数，避免等待中的线程错过唤醒
+            let result = {
+                let mut queue = scheduler.queue.lock().unwrap();
+                *scheduler.running.lock().unwrap().get_mut(&domain).unwrap() -= 1;
+                queue.finish(job, state)
+            };
+            scheduler.changed.notify_all();
+            result?;
+        }
+    }
+
+    fn execute(&self, mut job: Job) -> (Job, JobState) {
+        let extr = match get_extr(job.domain.as_str()) {
+            Some(extr) => extr,
+            None => {
+                let error = format!("Unsupported domain: {}", job.domain);
+                return (job, JobState::Failed { error });
+            }
+        };
+        let downloader = Downloader::new(&**extr)
+            .concurrency(self.page_concurrency)
+            .listener(self.listener.clone());
+        let state = match downloader.download(&mut job.chapter, &job.dir) {
+            Ok(summary) if summary.is_complete() => JobState::Done,
+            Ok(summary) => JobState::Failed {
+                error: format!(
+                    "{} of {} pages failed: {}",
+                    summary.failed.len(),
+                    summary.total,
+                    summary.failed[0].error
+                ),
+            },
+            Err(e) => JobState::Failed { error: e.to_string() },
+        };
+
+        (job, state)
+    }
+}
+
+#[test]
+fn test_queue() {
+    let root = std::env::temp_dir().join("mikack-test-queue");
+    let _ = fs::remove_dir_all(&root);
+    let path = root.join("queue.json");
+    let mut comic = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    for which in 1..=3 {
+        let mut chapter = Chapter::new(
+            format!("第{}话", which),
+            format!("https://www.manhuagui.com/comic/2863/{}.html", which),
+            which,
+        );
+        chapter.push_page(Page::new(0, "data:image/png;base64,iVBORw0KGgo="));
+        if which == 2 {
+            chapter.push_page(Page::new(1, "data:image/png;base64,!"));
+        }
+        comic.push_chapter(chapter);
+    }
+
+    let mut queue = Queue::open(&path).unwrap();
+    let ids = queue.push_comic(&comic, &Selection::All, &root).unwrap();
+    assert_eq!(vec![1, 2, 3], ids);
+    assert!(queue.push_comic(&comic, &Selection::All, &root).unwrap().is_empty());
+    queue.pause(3).unwrap();
+    assert!(queue.resume(1).is_err());
+    assert_eq!("www.manhuagui.com", queue.get(1).unwrap().domain);
+    let chapter = Chapter::from_url("http://comic3.ikkdm.com/comiclist/2023/46890/1.htm");
+    let id = queue.push(chapter, root.join("ikkdm")).unwrap();
+    assert_eq!("comic.ikkdm.com", queue.get(id).unwrap().domain);
+    queue.pause(id).unwrap();
+    assert!(queue.push(Chapter::from_url("https://example.com/1.html"), root.join("example")).is_err());
+
+    let manager = Manager::new(Queue::open(&path).unwrap()).concurrency(2);
+    manager.run().unwrap();
+
+    let queue = Queue::open(&path).unwrap();
+    assert_eq!(JobState::Done, queue.get(1).unwrap().state);
+    match &queue.get(2).unwrap().state {
+        JobState::Failed { error } => assert!(error.starts_with("1 of 2 pages failed")),
+        state => panic!("unexpected state: {:?}", state),
+    }
+    assert_eq!(JobState::Paused, queue.get(3).unwrap().state);
+    assert_eq!("1.png", queue.get(1).unwrap().chapter.pages[0].fname);
+    let _ = fs::remove_dir_all(&root);
+}
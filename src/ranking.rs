@@ -0,0 +1,192 @@
+//! 搜索结果的排序和去重。
+use crate::helper::normalize::{normalize_title, similarity, tokenize};
+use crate::matching;
+use crate::models::*;
+
+const COVERAGE_WEIGHT: f64 = 0.6;
+const SIMILARITY_WEIGHT: f64 = 0.25;
+const PREFIX_WEIGHT: f64 = 0.15;
+/// 只有作者匹配时的最高分数
+const AUTHOR_WEIGHT: f64 = 0.5;
+/// 不同来源的结果的匹配分数（见 `matching::score`）不低于此值时视为同一部漫画
+pub const DUPLICATE_SCORE: f64 = 0.85;
+
+/// 计算搜索结果与关键字的相关度（0-1）。
+///
+/// 标题与关键字规范化后相同时为 1，否则按关键字的词（见 `tokenize`）在标题中出现的比例、
+/// 标题的相似度以及是否以关键字开头计分，只有作者匹配时分数较低。
+pub fn relevance(keywords: &str, comic: &Comic) -> f64 {
+    let (normalized, title) = (normalize_title(keywords), normalize_title(&comic.title));
+    if normalized.is_empty() {
+        return 0.0;
+    }
+    if normalized == title {
+        return 1.0;
+    }
+    let tokens = tokenize(keywords);
+    let coverage = |text: &str| {
+        let other = tokenize(text);
+        tokens.iter().filter(|t| other.contains(t)).count() as f64 / tokens.len() as f64
+    };
+    let mut score = COVERAGE_WEIGHT * coverage(&comic.title) + SIMILARITY_WEIGHT * similarity(&normalized, &title);
+    if title.starts_with(&normalized) {
+        score += PREFIX_WEIGHT;
+    }
+
+    score.max(AUTHOR_WEIGHT * coverage(&comic.author))
+}
+
+/// 排序后的结果，同一部漫画在不同来源中的结果合并为一项。
+#[derive(Debug, Clone)]
+pub struct Ranked {
+    /// 合并的结果中最高的相关度
+    pub score: f64,
+    /// 包含此漫画的来源 `(来源域名, 漫画)`，按相关度从高到低排列
+    pub sources: Vec<(String, Comic)>,
+}
+
+impl Ranked {
+    /// 相关度最高的结果。
+    pub fn comic(&self) -> &Comic {
+        &self.sources[0].1
+    }
+
+    pub fn domains(&self) -> Vec<&str> {
+        self.sources.iter().map(|(domain, _)| domain.as_str()).collect()
+    }
+}
+
+/// 对多个来源的搜索结果排序并合并重复的漫画。
+pub struct Ranker {
+    min_score: f64,
+    group_duplicates: bool,
+}
+
+impl Default for Ranker {
+    fn default() -> Self {
+        Self {
+            min_score: 0.0,
+            group_duplicates: true,
+        }
+    }
+}
+
+impl Ranker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// 低于此相关度的结果会被丢弃，默认保留全部结果。
+    pub fn min_score(mut self, min_score: f64) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// 是否合并不同来源中的同一部漫画，默认合并。同一来源中的结果不会被合并。
+    pub fn group_duplicates(mut self, group_duplicates: bool) -> Self {
+        self.group_duplicates = group_duplicates;
+        self
+    }
+
+    /// 按相关度从高到低排序，相关度相同时来源较多的在前。
+    pub fn rank<I>(&self, keywords: &str, results: I) -> Vec<Ranked>
+    where
+        I: IntoIterator<Item = (String, Comic)>,
+    {
+        let mut scored = results
+            .into_iter()
+            .map(|(domain, comic)| (relevance(keywords, &comic), domain, comic))
+            .filter(|(score, _, _)| *score >= self.min_score)
+            .collect::<Vec<_>>();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        let mut ranked: Vec<Ranked> = vec![];
+        for (score, domain, comic) in scored {
+            let group = if self.group_duplicates {
+                // 与组内任意一项匹配即可，组内各项的标题写法可能不同
+                ranked.iter_mut().find(|r| {
+                    r.sources.iter().all(|(d, _)| *d != domain)
+                        && r.sources
+                            .iter()
+                            .any(|(_, other)| matching::score(other, &comic) >= DUPLICATE_SCORE)
+                })
+            } else {
+                None
+            };
+            match group {
+                Some(group) => group.sources.push((domain, comic)),
+                None => ranked.push(Ranked {
+                    score,
+                    sources: vec![(domain, comic)],
+                }),
+            }
+        }
+        ranked.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(b.sources.len().cmp(&a.sources.len()))
+        });
+
+        ranked
+    }
+}
+
+/// 使用默认选项排序并合并搜索结果。
+pub fn rank<I>(keywords: &str, results: I) -> Vec<Ranked>
+where
+    I: IntoIterator<Item = (String, Comic)>,
+{
+    Ranker::new().rank(keywords, results)
+}
+
+#[test]
+fn test_rank() {
+    let result = |domain: &str, title: &str, author: &str| {
+        let mut comic = Comic::new(title, format!("https://{}/{}", domain, title));
+        comic.author = author.to_string();
+        (domain.to_string(), comic)
+    };
+    let results = vec![
+        result("www.manhuagui.com", "食戟之灵 番外篇", "附田祐斗"),
+        result("www.manhuagui.com", "美食的俘虏", "岛袋光年"),
+        result("www.manhuagui.com", "食戟之灵", "附田祐斗"),
+        result("www.dm5.com", "食戟之靈", "附田祐斗 佐伯俊"),
+        result("www.dm5.com", "一拳超人", "ONE"),
+        result("www.mangabz.com", "【食戟之灵】", ""),
+    ];
+
+    let ranked = rank("食戟之灵", results.clone());
+    assert_eq!(4, ranked.len());
+    assert_eq!(1.0, ranked[0].score);
+    assert_eq!(
+        vec!["www.manhuagui.com", "www.dm5.com", "www.mangabz.com"],
+        ranked[0].domains()
+    );
+    assert_eq!("食戟之灵 番外篇", ranked[1].comic().title);
+    assert_eq!("美食的俘虏", ranked[2].comic().title);
+    assert_eq!("一拳超人", ranked[3].comic().title);
+    assert_eq!(0.0, ranked[3].score);
+
+    let ranked = Ranker::new()
+        .min_score(0.5)
+        .group_duplicates(false)
+        .rank("食戟之灵", results.clone());
+    let titles = ranked.iter().map(|r| r.comic().title.as_str()).collect::<Vec<_>>();
+    assert_eq!(vec!["食戟之灵", "食戟之靈", "【食戟之灵】", "食戟之灵 番外篇"], titles);
+    assert!(relevance("附田祐斗", &results[0].1) >= 0.5);
+    // 第三个来源的作者与组内第一项不同，但与没有作者信息的第二项匹配
+    let ranked = rank(
+        "食戟之灵",
+        vec![
+            result("www.manhuagui.com", "食戟之灵", "附田祐斗"),
+            result("www.mangabz.com", "食戟之灵", ""),
+            result("www.dm5.com", "食戟之灵", "佐伯俊"),
+        ],
+    );
+    assert_eq!(1, ranked.len());
+    assert_eq!(vec!["www.manhuagui.com", "www.mangabz.com", "www.dm5.com"], ranked[0].domains());
+
+    let soma = Comic::new("Shokugeki no Soma", "");
+    assert!(relevance("shokugeki", &soma) > relevance("soma", &soma));
+}
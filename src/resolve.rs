@@ -0,0 +1,92 @@
+//! 解析任意（粘贴的）地址。
+use crate::extractors::{domain_route, get_extr, is_gallery, DomainRoute, Extractor};
+use crate::{error::*, models::*};
+
+/// 解析的结果，包含已获取章节列表的漫画或已获取页面的章节，以及对应的提取器。
+pub enum Resolved {
+    Comic {
+        domain: String,
+        extr: &'static (dyn Extractor + Sync + Send),
+        comic: Comic,
+    },
+    Chapter {
+        domain: String,
+        extr: &'static (dyn Extractor + Sync + Send),
+        chapter: Chapter,
+    },
+}
+
+// 提取器没有实现 `Debug`，只输出域名和解析结果
+impl std::fmt::Debug for Resolved {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Resolved::Comic { domain, comic, .. } => f
+                .debug_struct("Comic")
+                .field("domain", domain)
+                .field("comic", comic)
+                .finish(),
+            Resolved::Chapter { domain, chapter, .. } => f
+                .debug_struct("Chapter")
+                .field("domain", domain)
+                .field("chapter", chapter)
+                .finish(),
+        }
+    }
+}
+
+impl Resolved {
+    pub fn domain(&self) -> &str {
+        match self {
+            Resolved::Comic { domain, .. } | Resolved::Chapter { domain, .. } => domain,
+        }
+    }
+
+    pub fn extr(&self) -> &'static (dyn Extractor + Sync + Send) {
+        match self {
+            Resolved::Comic { extr, .. } | Resolved::Chapter { extr, .. } => *extr,
+        }
+    }
+}
+
+/// 解析地址：漫画地址获取章节列表，章节地址获取全部页面。
+///
+/// 画廊类网站（见 `is_gallery`）的地址解析为只有一个章节的漫画，章节的页面已获取。
+pub fn resolve(url: &str) -> Result<Resolved> {
+    let route = domain_route(url).ok_or_else(|| err_msg(format!("Unsupported url: {}", url)))?;
+    let domain = match &route {
+        DomainRoute::Comic(domain) | DomainRoute::Chapter(domain) => domain.clone(),
+    };
+    let extr = get_extr(domain.as_str()).ok_or_else(|| err_msg(format!("Unsupported domain: {}", domain)))?;
+    let extr: &'static (dyn Extractor + Sync + Send) = &**extr;
+
+    match route {
+        DomainRoute::Comic(_) => {
+            let mut comic = Comic::from_url(url);
+            extr.fetch_chapters(&mut comic)?;
+            Ok(Resolved::Comic { domain, extr, comic })
+        }
+        DomainRoute::Chapter(_) => {
+            let mut chapter = Chapter::from_url(url);
+            for page in extr.pages_iter(&mut chapter)? {
+                page?;
+            }
+            if !is_gallery(&domain) {
+                return Ok(Resolved::Chapter { domain, extr, chapter });
+            }
+            let mut comic = Comic::new(chapter.title.clone(), url);
+            chapter.which = 1;
+            comic.push_chapter(chapter);
+            Ok(Resolved::Comic { domain, extr, comic })
+        }
+    }
+}
+
+#[test]
+fn test_resolve() {
+    assert!(is_gallery("e-hentai.org"));
+    assert!(!is_gallery("www.manhuagui.com"));
+    match resolve("https://example.com/comic/1/") {
+        Err(e) => assert_eq!("Unsupported url: https://example.com/comic/1/", e.to_string()),
+        Ok(resolved) => panic!("example.com should not be resolved: {:?}", resolved),
+    }
+}
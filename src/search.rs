@@ -0,0 +1,199 @@
+//! 跨来源的并行搜索。
+use crate::error::*;
+use crate::extractors::{get_extr, with_deadline};
+use crate::models::*;
+use crate::ranking::{rank, Ranked};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 单个来源的超时时间默认值。
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// 单个来源的搜索结果。
+#[derive(Debug, Clone)]
+pub struct SourceResults {
+    pub domain: String,
+    pub comics: Vec<Comic>,
+}
+
+/// 全部来源的搜索结果，按来源的传入顺序排列，搜索失败或超时的来源记录在 `errors` 中。
+#[derive(Debug, Clone, Default)]
+pub struct SearchResults {
+    pub sources: Vec<SourceResults>,
+    pub errors: Vec<(String, String)>,
+}
+
+impl SearchResults {
+    /// 全部来源的漫画 `(来源域名, 漫画)`。
+    pub fn comics(&self) -> impl Iterator<Item = (&str, &Comic)> {
+        self.sources
+            .iter()
+            .flat_map(|s| s.comics.iter().map(move |c| (s.domain.as_str(), c)))
+    }
+
+    /// 按与关键字的相关度排序，并合并不同来源中的同一部漫画（见 `ranking`）。
+    pub fn ranked(&self, keywords: &str) -> Vec<Ranked> {
+        rank(
+            keywords,
+            self.comics().map(|(domain, comic)| (domain.to_string(), comic.clone())),
+        )
+    }
+}
+
+/// 并行搜索多个来源。
+pub struct Search {
+    page: u32,
+    timeout: Duration,
+    concurrency: usize,
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Self {
+            page: 1,
+            timeout: DEFAULT_TIMEOUT,
+            concurrency: 8,
+        }
+    }
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// 搜索的页码（支持分页搜索时），默认为第 1 页。
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = page.max(1);
+        self
+    }
+
+    /// 单个来源的超时时间，从该来源开始搜索时计时，超时的来源记录为错误。
+    ///
+    /// 来源通过 `extractors::get` 发出的请求会在超时时中断，其它请求受 HTTP 客户端自身的超时限制。
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 同时搜索的来源数量，默认为 8。
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// 搜索 `sources` 中的来源，不支持搜索的来源会被忽略。返回前会等待所有搜索线程结束。
+    pub fn search<I, S>(&self, keywords: &str, sources: I) -> SearchResults
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut searchable = vec![];
+        let mut unsupported = vec![];
+        for domain in sources.into_iter().map(Into::into) {
+            match get_extr(domain.as_str()) {
+                Some(extr) if extr.is_searchable() => searchable.push((domain, extr)),
+                Some(_) => (),
+                None => {
+                    let error = format!("Unsupported domain: {}", domain);
+                    unsupported.push((domain, error));
+                }
+            }
+        }
+        let domains = searchable.iter().map(|(domain, _)| domain.clone()).collect();
+        let mut results = self.run(domains, |i| {
+            let extr = searchable[i].1;
+            let page = if extr.is_pageable_search() { self.page } else { 1 };
+            extr.paginated_search(keywords, page)
+        });
+        unsupported.append(&mut results.errors);
+        results.errors = unsupported;
+
+        results
+    }
+
+    // 以 `concurrency` 个线程执行 `search(下标)`，每个来源从开始搜索时单独计时
+    fn run<F>(&self, domains: Vec<String>, search: F) -> SearchResults
+    where
+        F: Fn(usize) -> Result<Vec<Comic>> + Sync,
+    {
+        let next = AtomicUsize::new(0);
+        let received = Mutex::new(vec![]);
+        thread::scope(|scope| {
+            for _ in 0..self.concurrency.min(domains.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= domains.len() {
+                        break;
+                    }
+                    let start = Instant::now();
+                    let result = with_deadline(start + self.timeout, || search(i));
+                    let result = if start.elapsed() > self.timeout {
+                        Err(format!("Timed out after {:?}", self.timeout))
+                    } else {
+                        result.map_err(|e| e.to_string())
+                    };
+                    received.lock().unwrap().push((i, result));
+                });
+            }
+        });
+
+        let mut received = received.into_inner().unwrap();
+        received.sort_by_key(|(i, _)| *i);
+        let mut results = SearchResults::default();
+        for ((_, result), domain) in received.into_iter().zip(domains) {
+            match result {
+                Ok(comics) => results.sources.push(SourceResults { domain, comics }),
+                Err(e) => results.errors.push((domain, e)),
+            }
+        }
+
+        results
+    }
+}
+
+/// 使用默认选项并行搜索多个来源，例如 `search_all("食戟之灵", find_platforms(vec![Tag::Chinese], vec![]).keys())`。
+pub fn search_all<I, S>(keywords: &str, sources: I) -> SearchResults
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    Search::new().search(keywords, sources)
+}
+
+#[test]
+fn test_search() {
+    // 不支持搜索的来源会被忽略，不会发出请求
+    let results = Search::new().search("食戟之灵", vec!["18h.animezilla.com", "example.com"]);
+    assert!(results.sources.is_empty());
+    assert_eq!(1, results.errors.len());
+    assert_eq!("Unsupported domain: example.com", results.errors[0].1);
+}
+
+#[test]
+fn test_search_timeout() {
+    let domains = ["slow.example.com", "fast.example.com", "failed.example.com"];
+    let results = Search::new()
+        .timeout(Duration::from_millis(100))
+        .concurrency(2)
+        .run(domains.iter().map(|d| d.to_string()).collect(), |i| match i {
+            0 => {
+                thread::sleep(Duration::from_millis(300));
+                Ok(vec![Comic::new("slow", "")])
+            }
+            1 => Ok(vec![Comic::new("fast", "")]),
+            _ => Err(err_msg("failed")),
+        });
+    // 慢的来源超时不影响之后的来源
+    assert_eq!(1, results.sources.len());
+    assert_eq!("fast.example.com", results.sources[0].domain);
+    assert_eq!(
+        vec![
+            ("slow.example.com".to_string(), "Timed out after 100ms".to_string()),
+            ("failed.example.com".to_string(), "failed".to_string()),
+        ],
+        results.errors
+    );
+}
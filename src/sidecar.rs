@@ -0,0 +1,149 @@
+//! 与下载内容放在一起的元数据文件（sidecar）。
+//!
+//! 漫画目录下保存 `comic.json`，章节目录下保存 `chapter.json`，格式如下：
+//!
+//! ```json
+//! { "schema_version": 1, "comic": { "title": "...", "url": "...", "chapters": [...], ... } }
+//! { "schema_version": 1, "chapter": { "title": "...", "url": "...", "which": 1, "pages": [...], ... } }
+//! ```
+//!
+//! - `comic` 和 `chapter` 为 `Comic`/`Chapter` 的 JSON 表示，`comic.json` 中的章节不包含页面
+//! - 读取时缺少的字段使用默认值，未知的字段会被忽略
+//! - 字段的语义或结构发生不兼容的变化时递增 `SCHEMA_VERSION`，并在 `MIGRATIONS` 中添加从上一版本升级的函数
+//! - 不含 `schema_version` 的文件视为版本 0，即直接序列化的模型
+use crate::{download, error::*, models::*};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+/// 当前的格式版本。
+pub const SCHEMA_VERSION: u64 = 1;
+
+pub const COMIC_FNAME: &str = "comic.json";
+pub const CHAPTER_FNAME: &str = "chapter.json";
+
+type Migration = fn(Value) -> Result<Value>;
+
+/// 第 `i` 个函数将版本 `i` 升级为版本 `i + 1`。
+static MIGRATIONS: &[Migration] = &[migrate_v0];
+
+// 版本 0：直接序列化的模型，根据字段判断是漫画还是章节
+fn migrate_v0(value: Value) -> Result<Value> {
+    let key = if value.get("chapters").is_some() {
+        "comic"
+    } else if value.get("pages").is_some() {
+        "chapter"
+    } else {
+        return Err(err_msg("Unrecognized metadata without schema version"));
+    };
+
+    Ok(json!({ "schema_version": 1, key: value }))
+}
+
+/// 将任意版本的元数据升级到当前版本。
+pub fn migrate(mut value: Value) -> Result<Value> {
+    let mut version = value
+        .get("schema_version")
+        .map(|v| {
+            v.as_u64()
+                .ok_or_else(|| err_msg(format!("Invalid schema version: {}", v)))
+        })
+        .transpose()?
+        .unwrap_or(0);
+    if version > SCHEMA_VERSION {
+        return Err(err_msg(format!(
+            "Unsupported schema version {} (newer than {})",
+            version, SCHEMA_VERSION
+        )));
+    }
+    while version < SCHEMA_VERSION {
+        value = MIGRATIONS[version as usize](value)?;
+        version += 1;
+    }
+
+    Ok(value)
+}
+
+fn to_json<T: Serialize>(key: &str, model: &T) -> Result<Vec<u8>> {
+    let value = json!({ "schema_version": SCHEMA_VERSION, key: model });
+    Ok(serde_json::to_vec_pretty(&value)?)
+}
+
+fn from_json<T: DeserializeOwned>(key: &str, json: &[u8]) -> Result<T> {
+    let mut value = migrate(serde_json::from_slice(json)?)?;
+    let model = value
+        .get_mut(key)
+        .map(Value::take)
+        .ok_or_else(|| err_msg(format!("Missing `{}` in metadata", key)))?;
+
+    Ok(serde_json::from_value(model)?)
+}
+
+pub fn comic_to_json(comic: &Comic) -> Result<Vec<u8>> {
+    let mut comic = comic.clone();
+    for chapter in comic.chapters.iter_mut() {
+        chapter.pages.clear();
+    }
+    to_json("comic", &comic)
+}
+
+pub fn comic_from_json(json: &[u8]) -> Result<Comic> {
+    from_json("comic", json)
+}
+
+pub fn chapter_to_json(chapter: &Chapter) -> Result<Vec<u8>> {
+    to_json("chapter", chapter)
+}
+
+pub fn chapter_from_json(json: &[u8]) -> Result<Chapter> {
+    from_json("chapter", json)
+}
+
+/// 写入漫画目录下的 `comic.json`。
+pub fn write_comic(comic_dir: &Path, comic: &Comic) -> Result<()> {
+    fs::create_dir_all(comic_dir)?;
+    download::save(comic_dir, COMIC_FNAME, &comic_to_json(comic)?)?;
+    Ok(())
+}
+
+pub fn read_comic(comic_dir: &Path) -> Result<Comic> {
+    comic_from_json(&fs::read(comic_dir.join(COMIC_FNAME))?)
+}
+
+/// 写入章节目录下的 `chapter.json`。
+pub fn write_chapter(chapter_dir: &Path, chapter: &Chapter) -> Result<()> {
+    fs::create_dir_all(chapter_dir)?;
+    download::save(chapter_dir, CHAPTER_FNAME, &chapter_to_json(chapter)?)?;
+    Ok(())
+}
+
+pub fn read_chapter(chapter_dir: &Path) -> Result<Chapter> {
+    chapter_from_json(&fs::read(chapter_dir.join(CHAPTER_FNAME))?)
+}
+
+#[test]
+fn test_sidecar() {
+    let mut comic = Comic::new("食戟之灵", "https://www.manhuagui.com/comic/2863/");
+    let mut chapter = Chapter::new("第1话", "https://www.manhuagui.com/comic/2863/1.html", 1);
+    chapter.push_page(Page::new(0, "https://i.hamreus.com/1.jpg.webp"));
+    comic.push_chapter(chapter.clone());
+
+    let json = comic_to_json(&comic).unwrap();
+    let value: Value = serde_json::from_slice(&json).unwrap();
+    assert_eq!(Some(SCHEMA_VERSION), value["schema_version"].as_u64());
+    let read = comic_from_json(&json).unwrap();
+    assert_eq!("食戟之灵", read.title);
+    assert!(read.chapters[0].pages.is_empty());
+    let read = chapter_from_json(&chapter_to_json(&chapter).unwrap()).unwrap();
+    assert_eq!("image/webp", read.pages[0].fmime);
+
+    // 版本 0（直接序列化的模型），且缺少部分字段
+    let v0 = r#"{"title": "第2话", "url": "https://www.manhuagui.com/comic/2863/2.html", "which": 2, "pages": []}"#.as_bytes();
+    let read = chapter_from_json(v0).unwrap();
+    assert_eq!(2, read.which);
+    assert!(read.page_headers.is_empty());
+    assert!(comic_from_json(v0).is_err());
+    assert!(chapter_from_json(br#"{"schema_version": 99, "chapter": {}}"#).is_err());
+}
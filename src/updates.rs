@@ -0,0 +1,156 @@
+use crate::extractors::Extractor;
+use crate::{error::*, models::*};
+use std::collections::HashMap;
+
+/// 新旧章节列表的差异。
+#[derive(Debug, Clone, Default)]
+pub struct ChapterDiff {
+    pub added: Vec<Chapter>,
+    pub removed: Vec<Chapter>,
+    /// 标题发生变化的章节 `(旧, 新)`
+    pub renamed: Vec<(Chapter, Chapter)>,
+}
+
+impl ChapterDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+}
+
+/// 章节地址中不随域名和协议变化的部分，例如 `https://www.manhuagui.com/comic/2863/271796.html`
+/// 的 `/comic/2863/271796.html`。
+pub fn stable_id(url: &str) -> String {
+    match url::Url::parse(url) {
+        Ok(parsed) => {
+            let mut id = parsed.path().trim_end_matches('/').to_string();
+            if let Some(query) = parsed.query() {
+                id.push('?');
+                id.push_str(query);
+            }
+            id
+        }
+        Err(_) => url.to_string(),
+    }
+}
+
+/// 比较新旧章节列表。
+///
+/// 由于网站插入章节后 `which` 会整体偏移，章节依次按地址、稳定 id（`stable_id`）和话数匹配，
+/// 话数只在两边都唯一时才用于匹配。
+pub fn diff_chapters(old: &[Chapter], new: &[Chapter]) -> ChapterDiff {
+    let matched_new = match_chapters(
+        old,
+        new,
+        &[
+            |c| Some(c.url.clone()),
+            |c| Some(stable_id(&c.url)),
+            |c| {
+                c.number()
+                    .map(|n| format!("{}/{}", c.volume().unwrap_or(0.0), n))
+            },
+        ],
+    );
+    let mut matched_old = vec![false; old.len()];
+    for i in matched_new.iter().flatten() {
+        matched_old[*i] = true;
+    }
+
+    let mut diff = ChapterDiff::default();
+    for (j, chapter) in new.iter().enumerate() {
+        match matched_new[j] {
+            Some(i) if old[i].title != chapter.title => diff.renamed.push((old[i].clone(), chapter.clone())),
+            Some(_) => (),
+            None => diff.added.push(chapter.clone()),
+        }
+    }
+    for (i, chapter) in old.iter().enumerate() {
+        if !matched_old[i] {
+            diff.removed.push(chapter.clone());
+        }
+    }
+
+    diff
+}
+
+/// 依次按 `keys` 匹配两个章节列表，返回 `new` 中每个章节在 `old` 中对应的位置。
+///
+/// 每一轮只使用在各自列表中唯一的键，已匹配的章节不参与后续的匹配。
+pub(crate) fn match_chapters(
+    old: &[Chapter],
+    new: &[Chapter],
+    keys: &[fn(&Chapter) -> Option<String>],
+) -> Vec<Option<usize>> {
+    let mut matched_old = vec![false; old.len()];
+    let mut matched_new = vec![None; new.len()];
+    for key in keys {
+        let index = |chapters: &[Chapter], matched: &dyn Fn(usize) -> bool| {
+            let mut index: HashMap<String, Option<usize>> = HashMap::new();
+            for (i, chapter) in chapters.iter().enumerate() {
+                if matched(i) {
+                    continue;
+                }
+                if let Some(k) = key(chapter) {
+                    index
+                        .entry(k)
+                        .and_modify(|v| *v = None)
+                        .or_insert(Some(i));
+                }
+            }
+            index
+        };
+        let old_index = index(old, &|i| matched_old[i]);
+        let new_index = index(new, &|i| matched_new[i].is_some());
+        for (k, j) in new_index {
+            if let (Some(j), Some(Some(i))) = (j, old_index.get(&k)) {
+                matched_old[*i] = true;
+                matched_new[j] = Some(*i);
+            }
+        }
+    }
+
+    matched_new
+}
+
+/// 重新获取漫画的章节列表，返回新的章节列表及与 `comic.chapters` 的差异。
+pub fn check_updates(extr: &(dyn Extractor + Sync + Send), comic: &Comic) -> Result<(Vec<Chapter>, ChapterDiff)> {
+    let mut fresh = Comic::new(comic.title.clone(), comic.url.clone());
+    extr.fetch_chapters(&mut fresh)?;
+    let diff = diff_chapters(&comic.chapters, &fresh.chapters);
+
+    Ok((fresh.chapters, diff))
+}
+
+#[test]
+fn test_diff_chapters() {
+    let chapter = |title: &str, path: &str, which: u32| {
+        Chapter::new(title, format!("https://www.manhuagui.com/comic/2863/{}", path), which)
+    };
+    let old = vec![
+        chapter("第1话", "1.html", 1),
+        chapter("第2话", "2.html", 2),
+        chapter("第3话", "3.html", 3),
+        chapter("番外", "sp.html", 4),
+    ];
+    let mut moved = chapter("第3话", "3-new.html", 4);
+    moved.url = moved.url.replace("https://www.", "http://m.");
+    let new = vec![
+        chapter("第1话 出发", "1.html", 1),
+        // 网站插入章节后 `which` 发生偏移
+        chapter("第1.5话", "1-5.html", 2),
+        Chapter {
+            url: String::from("http://tw.manhuagui.com/comic/2863/2.html"),
+            ..chapter("第2话", "", 3)
+        },
+        moved,
+        chapter("第4话", "4.html", 5),
+    ];
+
+    let diff = diff_chapters(&old, &new);
+    let titles = |chapters: &[Chapter]| chapters.iter().map(|c| c.title.clone()).collect::<Vec<_>>();
+    assert_eq!(vec!["第1.5话", "第4话"], titles(&diff.added));
+    assert_eq!(vec!["番外"], titles(&diff.removed));
+    assert_eq!(1, diff.renamed.len());
+    assert_eq!("第1话 出发", diff.renamed[0].1.title);
+    assert!(diff_chapters(&old, &old).is_empty());
+    assert_eq!("/comic/2863", stable_id("https://www.manhuagui.com/comic/2863/"));
+}
@@ -1,4 +1,7 @@
 use mikack::extractors::*;
+use mikack::search::Search;
+use mikack::{resolve, Resolved};
+use std::time::Duration;
 
 #[test]
 fn test_search() {
@@ -6,21 +9,52 @@ fn test_search() {
     let only_includes: Vec<String> = vec![];
     let keywords = "asdfghjkl"; // 测试无结果搜索时 API 的稳定性
 
-    for (domain, _) in platforms().iter() {
-        if only_includes.is_empty() {
-            if !ignored_list.contains(domain) {
-                get_extr(domain)
-                    .unwrap()
-                    .paginated_search(keywords, 1)
-                    .unwrap();
-            }
-        } else {
-            if only_includes.contains(domain) {
-                get_extr(domain)
-                    .unwrap()
-                    .paginated_search(keywords, 1)
-                    .unwrap();
-            }
+    let sources = platforms()
+        .keys()
+        .filter(|domain| {
+            !ignored_list.contains(*domain) && (only_includes.is_empty() || only_includes.contains(*domain))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    // 网络较慢时仍给每个来源足够的时间
+    let results = Search::new().timeout(Duration::from_secs(60)).search(keywords, sources);
+    assert!(results.errors.is_empty(), "{:?}", results.errors);
+}
+
+#[test]
+#[ignore]
+fn test_resolve_comic() {
+    match resolve("https://www.manhuagui.com/comic/2863/").unwrap() {
+        Resolved::Comic { domain, comic, .. } => {
+            assert_eq!("www.manhuagui.com", domain);
+            assert!(!comic.chapters.is_empty());
+        }
+        resolved => panic!("unexpected: {:?}", resolved),
+    }
+}
+
+#[test]
+#[ignore]
+fn test_resolve_chapter() {
+    match resolve("https://www.manhuagui.com/comic/2863/271796.html").unwrap() {
+        Resolved::Chapter { domain, chapter, .. } => {
+            assert_eq!("www.manhuagui.com", domain);
+            assert!(!chapter.pages.is_empty());
+        }
+        resolved => panic!("unexpected: {:?}", resolved),
+    }
+}
+
+#[test]
+#[ignore]
+fn test_resolve_gallery() {
+    // 画廊解析为只有一个章节的漫画
+    match resolve("https://e-hentai.org/g/1617973/3224dd8125/").unwrap() {
+        Resolved::Comic { domain, comic, .. } => {
+            assert_eq!("e-hentai.org", domain);
+            assert_eq!(1, comic.chapters.len());
+            assert!(!comic.chapters[0].pages.is_empty());
         }
+        resolved => panic!("unexpected: {:?}", resolved),
     }
 }
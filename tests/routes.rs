@@ -96,6 +96,10 @@ fn test_routes() {
         :comic   => "https://manganelo.com/manga/hgj2047065412",
         :chapter => "https://manganelo.com/chapter/hgj2047065412/chapter_43"
     );
+    assert_routes!("www.mangareader.net",
+        :comic   => "http://www.mangareader.net/onepunch-man",
+        :chapter => "http://www.mangareader.net/onepunch-man/131"
+    );
     assert_routes!("www.manhuadb.com",
         :comic   => "https://www.manhuadb.com/manhua/10906",
         :chapter => "https://www.manhuadb.com/manhua/10906/13071_183254.html"